@@ -1,17 +1,26 @@
 use rayon::prelude::*;
 use itertools::{Itertools, MultiProduct};
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::{HashMap, HashSet, BinaryHeap};
-use std::sync::Arc;
-use std::sync::atomic::AtomicU64;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use std::time::{Duration, Instant};
 
 use crate::airs::connection;
 
 use super::connection::Connection;
+use super::cache::OutputCache;
 use super::connection::ConnectionValue;
+use super::context::Context;
+use super::example::Example;
+use super::metric::{ColorDistance, GridMetric};
 use super::neuron::Neuron;
+use super::progress::{Progress, SearchObserver, SearchStats};
 use super::neuron::NeuronValue;
+use super::neuron::TypeBindings;
 use super::neuron::ValueType;
+use super::registry::NeuronRegistry;
 use super::utility::*;
 
 fn load_f64(a: &AtomicU64) -> f64 {
@@ -39,15 +48,401 @@ fn store_min(a: &AtomicU64, value: f64) {
     }
 }
 
+/// Best distinct connections found for a target, bounded to `top_k`
+struct Ranking<'a> {
+    top_k: usize,
+    pairs: Mutex<BinaryHeap<Reverse<Pair> > >,
+    threshold: AtomicU64,
+    progress: &'a Progress,
+}
+
+impl<'a> Ranking<'a> {
+    fn new(top_k: usize, progress: &'a Progress) -> Self {
+        Self {
+            top_k,
+            pairs: Mutex::new(BinaryHeap::with_capacity(top_k + 1)),
+            threshold: AtomicU64::new(f64::INFINITY.to_bits()),
+            progress,
+        }
+    }
+
+    /// Cost a candidate has to beat to enter the ranking
+    fn threshold(&self) -> f64 {
+        load_f64(&self.threshold)
+    }
+
+    fn insert(&self, cost: f64, connection: impl FnOnce() -> Arc<Connection>) {
+        if cost >= self.threshold() {
+            return;
+        }
+
+        let connection = connection();
+        let mut pairs = self.pairs.lock().expect("Lock poisoned");
+
+        if pairs.iter().any(|r| r.0.connection == connection) {
+            return;
+        }
+
+        self.progress.candidate(cost, &connection);
+
+        pairs.push(Reverse(Pair {
+            cost,
+            connection_cost: connection.cost(),
+            connection,
+            failures: Vec::new(),
+        }));
+
+        if pairs.len() > self.top_k {
+            pairs.pop();
+        }
+
+        if pairs.len() == self.top_k {
+            if let Some(worst) = pairs.peek() {
+                store_min(&self.threshold, worst.0.cost);
+            }
+        }
+    }
+
+    /// Put `connection` in place of `old`, a connection of the same output value, inserting it if `old`
+    /// is not ranked
+    fn replace(&self, old: &Arc<Connection>, cost: f64, connection: Arc<Connection>) {
+        let mut pairs = self.pairs.lock().expect("Lock poisoned");
+
+        if pairs.iter().any(|r| Arc::ptr_eq(&r.0.connection, old)) {
+            let mut ranked = std::mem::take(&mut *pairs).into_vec();
+
+            for r in ranked.iter_mut().filter(|r| Arc::ptr_eq(&r.0.connection, old)) {
+                r.0.connection_cost = connection.cost();
+                r.0.connection = Arc::clone(&connection);
+            }
+
+            self.progress.candidate(cost, &connection);
+            *pairs = ranked.into();
+            return;
+        }
+
+        drop(pairs);
+        self.insert(cost, || connection);
+    }
+
+    /// Ranked pairs, best first, ties of cost broken by the written program
+    fn into_sorted(self) -> Vec<Pair> {
+        let mut pairs: Vec<Pair> = self.pairs
+            .into_inner()
+            .expect("Lock poisoned")
+            .into_iter()
+            .map(|r| r.0)
+            .collect();
+
+        pairs.sort_by(|a, b| b.cmp(a).then_with(|| a.connection.to_string().cmp(&b.connection.to_string())));
+        pairs
+    }
+}
+
+/// Record the folds of `config.holdout` failed by the ranked connections reaching `eps`, then rank these
+/// connections by their number of failures, the best one becoming the connection of the target
+pub(super) fn validate(search: &mut Search, config: &SearchConfig) {
+    if config.holdout.is_empty() {
+        return;
+    }
+
+    for (pairs, connection) in search.ranked.iter_mut().zip(search.connections.iter_mut()) {
+        pairs.par_iter_mut().filter(|pair| pair.cost < config.eps).for_each(|pair| {
+            pair.failures = config
+                .holdout
+                .iter()
+                .enumerate()
+                .filter(|(_, fold)| {
+                    pair.connection
+                        .output_in(&fold.context)
+                        .map(|v| config.heuristic(&v, &fold.output))
+                        .is_none_or(|cost| cost >= config.eps)
+                })
+                .map(|(i, _)| i)
+                .collect();
+        });
+
+        pairs.sort_by_key(|pair| (pair.cost >= config.eps, pair.failures.len()));
+
+        if let (Ok(connection), Some(best)) = (connection.as_mut(), pairs.first()) {
+            *connection = Arc::clone(&best.connection);
+        }
+    }
+}
+
+/// Bindings unifying each polymorphic input type with one of `types`
+fn signature_bindings(inputs: &[ValueType], types: &[ValueType], bindings: TypeBindings) -> Vec<TypeBindings> {
+    let Some((input, rest)) = inputs.split_first() else {
+        return vec![bindings];
+    };
+
+    if !input.substitute(&bindings).is_polymorphic() {
+        return signature_bindings(rest, types, bindings);
+    }
+
+    types
+        .iter()
+        .filter_map(|t| {
+            let mut bindings = bindings.clone();
+
+            input.unify(t, &mut bindings).then_some(bindings)
+        })
+        .flat_map(|bindings| signature_bindings(rest, types, bindings))
+        .collect()
+}
+
+/// Connection without holes, with its output value and depth
+type Closed = (Arc<Connection>, NeuronValue, usize);
+
+/// Whether `a` represents its output value rather than `b`: the cheaper one, ties broken by the written
+/// program so that the order of the evaluations does not matter
+fn cheaper(a: &Connection, b: &Connection) -> bool {
+    a.cost() < b.cost() || a.cost() == b.cost() && a.to_string() < b.to_string()
+}
+
+fn search_error(
+    target_type: ValueType,
+    exhausted: bool,
+    candidate_types: &HashSet<ValueType>,
+    missing_parameters: &HashMap<ValueType, ValueType>,
+) -> SearchError {
+    if exhausted {
+        SearchError::BudgetExceeded
+    } else if candidate_types.contains(&target_type) {
+        SearchError::NoSolution(target_type)
+    } else if let Some(input_type) = missing_parameters.get(&target_type) {
+        SearchError::NoParameters(input_type.clone())
+    } else {
+        SearchError::NoCandidates(target_type)
+    }
+}
+
 pub struct Brain {
     neurons: Vec<Arc<Neuron> >,
 }
 
+/// How candidate connections are enumerated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Build every connection template up to `max_level` and fill its holes with constant neurons.
+    /// The default: unlike observational equivalence it keeps the programs agreeing on the train pairs
+    /// as distinct candidates, which the second submission attempt and the leave-one-out validation
+    /// tell apart on inputs they disagree on
+    #[default]
+    Exhaustive,
+    /// Build closed connections bottom-up, level by level, keeping only the cheapest connection
+    /// per distinct output value so that equivalent programs are not expanded again
+    ObservationalEquivalence,
+    /// Expand closed connections in order of their heuristic cost to the target plus their
+    /// connection cost, so that deep solutions are reached without enumerating every shallower one
+    BestFirst,
+}
+
+/// Shared flag to stop a running search from another thread
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// Search parameters: depth of the connections, accepted cost and optional budgets
+#[derive(Clone, Debug)]
+pub struct SearchConfig {
+    pub max_level: usize,
+    pub eps: f64,
+    pub deadline: Option<Instant>,
+    pub max_evaluations: Option<usize>,
+    pub cancellation: Option<CancellationToken>,
+    pub top_k: usize,
+    pub strategy: Strategy,
+    pub sketches: Vec<Arc<Connection> >,
+    pub grid_metric: Arc<dyn GridMetric>,
+    pub holdout: Vec<Example>,
+    pub cache: Option<Arc<OutputCache> >,
+    pub observer: Option<Arc<dyn SearchObserver> >,
+}
+
+impl SearchConfig {
+    pub fn new(max_level: usize, eps: f64) -> Self {
+        Self {
+            max_level,
+            eps,
+            deadline: None,
+            max_evaluations: None,
+            cancellation: None,
+            top_k: 1,
+            strategy: Strategy::default(),
+            sketches: Vec::new(),
+            grid_metric: Arc::new(ColorDistance),
+            holdout: Vec::new(),
+            cache: None,
+            observer: None,
+        }
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn with_max_evaluations(mut self, max_evaluations: usize) -> Self {
+        self.max_evaluations = Some(max_evaluations);
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Keep the `top_k` best distinct connections per target; the search of a target stops
+    /// once all of them are under `eps`
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Only search connections of the shape of `sketch`, filling its `ValueType` holes with
+    /// closed connections of depth up to `max_level`; the strategy is then ignored
+    pub fn with_sketch(mut self, sketch: Arc<Connection>) -> Self {
+        self.sketches.push(sketch);
+        self
+    }
+
+    /// Compare grids to the targets with `grid_metric` instead of `ColorDistance`
+    pub fn with_grid_metric(mut self, grid_metric: Arc<dyn GridMetric>) -> Self {
+        self.grid_metric = grid_metric;
+        self
+    }
+
+    /// Re-evaluate the ranked connections reaching `eps` on each fold, an example holding out one train
+    /// pair with the neurons derived from the train data bound to their values on the other pairs, and
+    /// rank them by the number of folds they fail; only the `top_k` ranked connections are validated
+    pub fn with_leave_one_out(mut self, folds: Vec<Example>) -> Self {
+        self.holdout = folds;
+        self
+    }
+
+    /// Memoize the outputs of the sub-connections shared by the candidates in `cache`, which can be shared
+    /// by concurrent searches over the same neurons; the closed connections built bottom-up already carry
+    /// their outputs, so their entries are only hit when they are evaluated again, as sketch holes or by
+    /// another search
+    pub fn with_cache(mut self, cache: Arc<OutputCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Report the statistics of the search to `observer` while it runs
+    pub fn with_observer(mut self, observer: Arc<dyn SearchObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Output of `connection`, through the cache of the search if any
+    pub fn output(&self, connection: &Connection) -> Option<NeuronValue> {
+        match &self.cache {
+            Some(cache) => connection.output_cached(&Context::new(), cache),
+            None => connection.output(),
+        }
+    }
+
+    /// Output of `connection` with `inputs`, through the cache of the search if any
+    pub fn output_with_inputs(&self, connection: &Connection, inputs: &[ConnectionValue]) -> Option<NeuronValue> {
+        match &self.cache {
+            Some(cache) => connection.output_with_inputs_cached(inputs, cache),
+            None => connection.output_with_inputs(inputs),
+        }
+    }
+
+    /// Output of `neuron` applied to `values`, the outputs of the connections `inputs`, memoized in the
+    /// cache of the search if any as the output of the connection of `neuron` to `inputs`
+    pub fn apply(&self, neuron: &Arc<Neuron>, inputs: &[ConnectionValue], values: &[NeuronValue]) -> Option<NeuronValue> {
+        match &self.cache {
+            Some(cache) => {
+                let connection = Arc::new(Connection::new(Arc::clone(neuron), inputs));
+
+                cache.get_or_insert_with(&connection, &[], Context::new().key(), || neuron.apply(values))
+            }
+            None => neuron.apply(values),
+        }
+    }
+
+    /// Cost of `value` for `target` under the grid metric of the search
+    pub fn heuristic(&self, value: &NeuronValue, target: &NeuronValue) -> f64 {
+        value.distance(target, self.grid_metric.as_ref())
+    }
+
+    fn exhausted(&self, evaluations: usize) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+            || self.max_evaluations.is_some_and(|n| evaluations >= n)
+            || self.cancellation.as_ref().is_some_and(|c| c.is_cancelled())
+    }
+}
+
+/// Reason why no connection was found for a target
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchError {
+    /// No neuron outputs the type of the target
+    NoCandidates(ValueType),
+    /// Connections output the type of the target but no constant neuron provides this input type
+    NoParameters(ValueType),
+    /// Every candidate failed to produce a value comparable to the target
+    NoSolution(ValueType),
+    /// The budget was exhausted before any candidate was found
+    BudgetExceeded,
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::NoCandidates(t) => write!(f, "No candidate connection outputs {:?}", t),
+            SearchError::NoParameters(t) => write!(f, "No parameter for input type {:?}", t),
+            SearchError::NoSolution(t) => write!(f, "No candidate connection produces a comparable {:?}", t),
+            SearchError::BudgetExceeded => write!(f, "Search budget exceeded before any candidate was found"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+/// Result of a search: the best connection or the failure per target, the `top_k` best distinct connections
+/// per target sorted by increasing cost and the number of evaluated candidates;
+/// `exhausted` tells whether the budget stopped the search before it was complete
+pub struct Search {
+    pub connections: Vec<Result<Arc<Connection>, SearchError> >,
+    pub ranked: Vec<Vec<Pair> >,
+    pub evaluations: usize,
+    pub exhausted: bool,
+    pub stats: SearchStats,
+}
+
 #[derive(Clone)]
 pub struct Pair {
     pub cost: f64,
     pub connection_cost: usize,
     pub connection: Arc<Connection>,
+    /// Folds of the leave-one-out validation failed by the connection, empty without validation
+    pub failures: Vec<usize>,
 }
 
 impl Ord for Pair {
@@ -73,6 +468,32 @@ impl PartialEq for Pair {
 
 impl Eq for Pair {}
 
+/// Connection of the best-first frontier with its output value, ordered as its pair
+struct FrontierNode {
+    pair: Pair,
+    value: NeuronValue,
+}
+
+impl Ord for FrontierNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.pair.cmp(&other.pair)
+    }
+}
+
+impl PartialOrd for FrontierNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FrontierNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.pair == other.pair
+    }
+}
+
+impl Eq for FrontierNode {}
+
 impl Brain {
     pub fn new(neurons: Vec<Arc<Neuron> >) -> Self {
         Self
@@ -81,12 +502,130 @@ impl Brain {
         }
     }
 
+    pub fn from_registry(registry: &NeuronRegistry) -> Self {
+        Self::new(registry.neurons().to_vec())
+    }
+
     pub fn learn(
         &self,
         targets: &[NeuronValue],
         max_level: usize,
         eps: f64,
-    ) -> Vec<Arc<Connection> > {
+    ) -> Vec<Result<Arc<Connection>, SearchError> > {
+        self.search(targets, &SearchConfig::new(max_level, eps)).connections
+    }
+
+    /// Search the best connection for each target within the budget of `config`;
+    /// once exhausted, the search stops and returns the best-so-far connections
+    pub fn search(
+        &self,
+        targets: &[NeuronValue],
+        config: &SearchConfig,
+    ) -> Search {
+        let target_types: Vec<ValueType> = targets.iter().map(NeuronValue::value_type).collect();
+
+        self.search_typed(targets, &target_types, config)
+    }
+
+    /// Same as `search`, the errors of each target naming `target_types` rather than the type of its value,
+    /// such as the type of the values of a per-example target
+    pub(super) fn search_typed(
+        &self,
+        targets: &[NeuronValue],
+        target_types: &[ValueType],
+        config: &SearchConfig,
+    ) -> Search {
+        if self.neurons.iter().any(|n| n.is_polymorphic()) {
+            return Brain::new(self.instantiated(targets, config)).search_typed(targets, target_types, config);
+        }
+
+        let progress = Progress::new(config);
+        let mut search = if !config.sketches.is_empty() {
+            self.search_sketches(targets, target_types, config, &progress)
+        } else {
+            match config.strategy {
+                Strategy::Exhaustive => self.search_exhaustive(targets, target_types, config, &progress),
+                Strategy::ObservationalEquivalence => {
+                    self.search_observational_equivalence(targets, target_types, config, &progress)
+                }
+                Strategy::BestFirst => self.search_best_first(targets, target_types, config, &progress),
+            }
+        };
+
+        validate(&mut search, config);
+
+        let best = search
+            .ranked
+            .iter()
+            .filter_map(|pairs| pairs.first())
+            .min_by(|a, b| a.cost.total_cmp(&b.cost))
+            .map(|pair| (pair.cost, pair.connection.as_ref()));
+
+        search.stats = progress.finish(search.evaluations, best);
+        search
+    }
+
+    /// Monomorphic neurons, the polymorphic ones being replaced by their instances at the types of the
+    /// other neurons, of the targets and of the sketch holes; as instances output new types, this repeats
+    /// for up to `max_level` rounds
+    pub(super) fn instantiated(&self, targets: &[NeuronValue], config: &SearchConfig) -> Vec<Arc<Neuron> > {
+        let (polymorphic, mut neurons): (Vec<Arc<Neuron> >, Vec<Arc<Neuron> >) =
+            self.neurons.iter().cloned().partition(|n| n.is_polymorphic());
+        let mut types: Vec<ValueType> = Vec::new();
+        let mut signatures: HashSet<(String, Vec<ValueType>)> = HashSet::new();
+
+        let candidates = neurons
+            .iter()
+            .flat_map(|n| n.input_types().iter().chain([n.output_type()]).cloned().collect::<Vec<_> >())
+            .chain(targets.iter().map(NeuronValue::value_type))
+            .chain(config.sketches.iter().flat_map(|s| s.holes()));
+
+        for t in candidates {
+            if !types.contains(&t) {
+                types.push(t);
+            }
+        }
+
+        for _ in 0..config.max_level.max(1) {
+            let mut instances: Vec<Arc<Neuron> > = Vec::new();
+
+            for neuron in &polymorphic {
+                for bindings in signature_bindings(neuron.input_types(), &types, TypeBindings::new()) {
+                    let instance = neuron.instantiate(&bindings);
+
+                    if !instance.is_polymorphic()
+                        && signatures.insert((instance.name(), instance.input_types().to_vec()))
+                    {
+                        instances.push(Arc::new(instance));
+                    }
+                }
+            }
+
+            if instances.is_empty() {
+                break;
+            }
+
+            for instance in &instances {
+                if !types.contains(instance.output_type()) {
+                    types.push(instance.output_type().clone());
+                }
+            }
+
+            neurons.extend(instances);
+        }
+
+        neurons
+    }
+
+    fn search_exhaustive(
+        &self,
+        targets: &[NeuronValue],
+        target_types: &[ValueType],
+        config: &SearchConfig,
+        progress: &Progress,
+    ) -> Search {
+        let eps = config.eps;
+        let exhausted = AtomicBool::new(false);
         let mut connections: HashSet<Arc<Connection> > = Default::default();
         let mut parameters: HashMap<ValueType, Vec<Arc<Connection> > > = Default::default();
 
@@ -130,10 +669,22 @@ impl Brain {
         let mut connection_mapping: HashMap<ValueType, HashSet<Arc<Connection> > > =
             HashMap::new();
 
-        for _ in 0..max_level {
+        progress.generated(0, self.neurons.iter().filter(|n| n.input_types().is_empty()).count());
+        progress.level(0, 0);
+
+        for level in 0..config.max_level {
+            if exhausted.load(std::sync::atomic::Ordering::Relaxed) {
+                break;
+            }
+
             let mut mapping = connection_mapping.clone();
             
             for connection in &connections {
+                if config.exhausted(0) {
+                    exhausted.store(true, std::sync::atomic::Ordering::Relaxed);
+                    break;
+                }
+
                 let neuron = connection.neuron();
                 let output_type = neuron.output_type().clone();
                 let input_types = connection.input_types();
@@ -167,9 +718,15 @@ impl Brain {
                 }
             }
 
+            let generated = mapping.values().map(HashSet::len).sum::<usize>()
+                - connection_mapping.values().map(HashSet::len).sum::<usize>();
+
             connection_mapping = mapping;
             connections.clear();
 
+            progress.generated(level + 1, generated);
+            progress.level(level + 1, 0);
+
             for set in connection_mapping.values() {
                 for conn in set {
                     connections.insert(conn.clone());
@@ -186,75 +743,576 @@ impl Brain {
         let mut conns = connections.clone().into_iter().collect::<Vec<_> >();
         conns.sort_by_key(|x| x.cost());
         
-        let connection_args: Vec<(Arc<Connection>, Vec<Vec<ConnectionValue> >)> =
-            conns
-                .iter()
-                .filter_map(|conn| {
-                    let mut args = Vec::new();
+        let mut connection_args: Vec<(Arc<Connection>, Vec<Vec<ConnectionValue> >)> = Vec::new();
+        let mut missing_parameters: HashMap<ValueType, ValueType> = HashMap::new();
 
-                    for input_type in conn.input_types() {
-                        let values = parameters.get(&input_type)?;
-                        args.push(
-                            values
-                                .iter()
-                                .cloned()
-                                .map(ConnectionValue::Connection)
-                                .collect()
-                        );
+        'conns: for conn in &conns {
+            let mut args = Vec::new();
+
+            for input_type in conn.input_types() {
+                match parameters.get(&input_type) {
+                    Some(values) => args.push(
+                        values
+                            .iter()
+                            .cloned()
+                            .map(ConnectionValue::Connection)
+                            .collect()
+                    ),
+                    None => {
+                        missing_parameters
+                            .entry(conn.neuron().output_type().clone())
+                            .or_insert(input_type);
+
+                        continue 'conns;
                     }
+                }
+            }
 
-                    Some((conn.clone(), args))
-                })
-                .collect();
+            connection_args.push((conn.clone(), args));
+        }
 
-        let global_best = Arc::new(AtomicU64::new(f64::INFINITY.to_bits()));
+        let evaluations = AtomicUsize::new(0);
 
-        targets
+        let ranked: Vec<Vec<Pair> > = targets
             .par_iter()
             .map(|target| {
-                let local_best = Arc::new(AtomicU64::new(f64::INFINITY.to_bits()));
+                let ranking = Ranking::new(config.top_k.max(1), progress);
 
                 connection_args
                     .par_iter()
-                    .flat_map_iter( |(conn, args)| {
-                        let conn = conn.clone();
-                        let global_best = Arc::clone(&global_best);
-                        let local_best = Arc::clone(&local_best);
-
+                    .for_each(|(conn, args)| {
                         args.iter()
                             .multi_cartesian_product()
-                            .filter_map(move |params| {
-                                if load_f64(&global_best) < eps {
-                                    return None;
+                            .take_while(|_| {
+                                if ranking.threshold() < eps {
+                                    return false;
                                 }
 
+                                if config.exhausted(evaluations.load(std::sync::atomic::Ordering::Relaxed)) {
+                                    exhausted.store(true, std::sync::atomic::Ordering::Relaxed);
+                                    return false;
+                                }
+
+                                true
+                            })
+                            .for_each(|params| {
+                                progress.evaluated(evaluations.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+
                                 let inputs: Vec<ConnectionValue> = params.iter().cloned().cloned().collect();
-                                let cost = conn
-                                    .output_with_inputs(&inputs)
-                                    .map(|v| v.heuristic(target))
+                                let cost = config
+                                    .output_with_inputs(conn, &inputs)
+                                    .map(|v| config.heuristic(&v, target))
                                     .unwrap_or(f64::INFINITY);
 
-                                if cost >= load_f64(&local_best) {
-                                    return None;
-                                }
+                                ranking.insert(cost, || {
+                                    let new_conn = Arc::new(conn.deep_clone());
+                                    new_conn.apply_inputs(&inputs);
+                                    new_conn
+                                });
+                            });
+                    });
 
-                                store_min(&local_best, cost);
-                                store_min(&global_best, cost);
+                ranking.into_sorted()
+            })
+            .collect();
 
-                                let new_conn = conn.clone();
-                                new_conn.apply_inputs(&inputs);
+        let candidate_types: HashSet<ValueType> = connection_args
+            .iter()
+            .map(|(conn, _)| conn.neuron().output_type().clone())
+            .collect();
+        let exhausted = exhausted.load(std::sync::atomic::Ordering::Relaxed);
 
-                                Some(Pair {
-                                    cost,
-                                    connection_cost: conn.cost(),
-                                    connection: new_conn,
-                                })
-                            })
-                    })
-                    .reduce_with(|a, b| if a.cost < b.cost { a } else { b })
-                    .expect("No solution found")
-                    .connection
+        let connections = target_types
+            .iter()
+            .zip(&ranked)
+            .map(|(target_type, pairs)| {
+                pairs
+                    .first()
+                    .map(|pair| pair.connection.clone())
+                    .ok_or_else(|| search_error(target_type.clone(), exhausted, &candidate_types, &missing_parameters))
+            })
+            .collect();
+
+        Search {
+            connections,
+            ranked,
+            evaluations: evaluations.load(std::sync::atomic::Ordering::Relaxed),
+            exhausted,
+            stats: SearchStats::default(),
+        }
+    }
+
+    fn search_observational_equivalence(
+        &self,
+        targets: &[NeuronValue],
+        target_types: &[ValueType],
+        config: &SearchConfig,
+        progress: &Progress,
+    ) -> Search {
+        let mut evaluations = 0;
+        let (bank, exhausted) = self.closed_connections(config.max_level, config, progress, &mut evaluations);
+
+        // Inserted by cost, then connection cost, then written program, the ranking keeps the same
+        // connections among equally close ones whatever the order of the bank
+        let mut closed: Vec<&Closed> = bank.values().flatten().collect();
+
+        closed.sort_by_cached_key(|(conn, _, _)| (conn.cost(), conn.to_string()));
+
+        let ranked: Vec<Vec<Pair> > = targets
+            .par_iter()
+            .map(|target| {
+                let ranking = Ranking::new(config.top_k.max(1), progress);
+                let mut costs: Vec<(f64, &Arc<Connection>)> = closed
+                    .iter()
+                    .map(|(conn, value, _)| (config.heuristic(value, target), conn))
+                    .collect();
+
+                costs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                for (cost, conn) in costs {
+                    ranking.insert(cost, || Arc::clone(conn));
+                }
+
+                ranking.into_sorted()
+            })
+            .collect();
+
+        let candidate_types: HashSet<ValueType> = bank.keys().cloned().collect();
+        let missing_parameters = self.missing_parameters(&candidate_types);
+
+        let connections = target_types
+            .iter()
+            .zip(&ranked)
+            .map(|(target_type, pairs)| {
+                pairs
+                    .first()
+                    .map(|pair| pair.connection.clone())
+                    .ok_or_else(|| search_error(target_type.clone(), exhausted, &candidate_types, &missing_parameters))
+            })
+            .collect();
+
+        Search {
+            connections,
+            ranked,
+            evaluations,
+            exhausted,
+            stats: SearchStats::default(),
+        }
+    }
+
+    fn search_best_first(
+        &self,
+        targets: &[NeuronValue],
+        target_types: &[ValueType],
+        config: &SearchConfig,
+        progress: &Progress,
+    ) -> Search {
+        let evaluations = AtomicUsize::new(0);
+        let exhausted = AtomicBool::new(false);
+
+        let results: Vec<(Vec<Pair>, HashSet<ValueType>)> = targets
+            .par_iter()
+            .map(|target| self.best_first(target, config, progress, &evaluations, &exhausted))
+            .collect();
+
+        let candidate_types: HashSet<ValueType> = results
+            .iter()
+            .flat_map(|(_, types)| types.iter().cloned())
+            .collect();
+        let missing_parameters = self.missing_parameters(&candidate_types);
+        let ranked: Vec<Vec<Pair> > = results.into_iter().map(|(pairs, _)| pairs).collect();
+        let exhausted = exhausted.load(std::sync::atomic::Ordering::Relaxed);
+
+        let connections = target_types
+            .iter()
+            .zip(&ranked)
+            .map(|(target_type, pairs)| {
+                pairs
+                    .first()
+                    .map(|pair| pair.connection.clone())
+                    .ok_or_else(|| search_error(target_type.clone(), exhausted, &candidate_types, &missing_parameters))
             })
-            .collect()
+            .collect();
+
+        Search {
+            connections,
+            ranked,
+            evaluations: evaluations.load(std::sync::atomic::Ordering::Relaxed),
+            exhausted,
+            stats: SearchStats::default(),
+        }
+    }
+
+    /// A* over closed connections: the cheapest connection of the frontier is combined with the
+    /// already expanded ones as inputs of every neuron accepting it, each distinct output value once,
+    /// by the cheapest connection generated before it is expanded.
+    /// Returns the ranking and the value types produced
+    fn best_first(
+        &self,
+        target: &NeuronValue,
+        config: &SearchConfig,
+        progress: &Progress,
+        evaluations: &AtomicUsize,
+        exhausted: &AtomicBool,
+    ) -> (Vec<Pair>, HashSet<ValueType>) {
+        let ranking = Ranking::new(config.top_k.max(1), progress);
+        // The connection representing each output value, and whether it was expanded
+        let mut seen: HashMap<NeuronValue, (Arc<Connection>, bool)> = HashMap::new();
+        let mut types: HashSet<ValueType> = HashSet::new();
+        let mut expanded: HashMap<ValueType, Vec<Closed> > = HashMap::new();
+        let mut frontier: BinaryHeap<FrontierNode> = BinaryHeap::new();
+
+        let mut push = |conn: Arc<Connection>,
+                        value: NeuronValue,
+                        frontier: &mut BinaryHeap<FrontierNode>,
+                        seen: &mut HashMap<NeuronValue, (Arc<Connection>, bool)>| {
+            let replaced = match seen.get(&value) {
+                None => None,
+                Some((existing, false)) if cheaper(&conn, existing) => Some(Arc::clone(existing)),
+                Some(_) => return,
+            };
+            let cost = config.heuristic(&value, target);
+            let connection_cost = conn.cost();
+
+            types.insert(conn.neuron().output_type().clone());
+            progress.generated(conn.depth(0), 1);
+
+            match replaced {
+                Some(old) => ranking.replace(&old, cost, Arc::clone(&conn)),
+                None => ranking.insert(cost, || Arc::clone(&conn)),
+            }
+
+            seen.insert(value.clone(), (Arc::clone(&conn), false));
+            frontier.push(FrontierNode {
+                pair: Pair {
+                    cost: cost + connection_cost as f64,
+                    connection_cost,
+                    connection: conn,
+                    failures: Vec::new(),
+                },
+                value,
+            });
+        };
+
+        for neuron in &self.neurons {
+            if neuron.input_types().is_empty() {
+                let conn = Arc::new(Connection::new(Arc::clone(neuron), &[]));
+
+                progress.evaluated(evaluations.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+
+                if let Some(value) = config.output(&conn) {
+                    push(conn, value, &mut frontier, &mut seen);
+                }
+            }
+        }
+
+        while let Some(FrontierNode { pair: Pair { connection, .. }, value }) = frontier.pop() {
+            if ranking.threshold() < config.eps {
+                break;
+            }
+
+            let depth = connection.depth(0);
+
+            if depth >= config.max_level {
+                continue;
+            }
+
+            // A cheaper connection of the same value replaced this one in the frontier
+            match seen.get_mut(&value) {
+                Some((representative, done)) if Arc::ptr_eq(representative, &connection) => *done = true,
+                _ => continue,
+            }
+
+            let value_type = connection.neuron().output_type().clone();
+
+            expanded
+                .entry(value_type.clone())
+                .or_default()
+                .push((Arc::clone(&connection), value, depth));
+
+            let node = expanded[&value_type].last().expect("Node just expanded").clone();
+
+            for neuron in &self.neurons {
+                let input_types = neuron.input_types();
+
+                for (i, _) in input_types.iter().enumerate().filter(|(_, t)| **t == value_type) {
+                    let args: Option<Vec<Vec<&Closed> > > = input_types
+                        .iter()
+                        .enumerate()
+                        .map(|(j, t)| match j.cmp(&i) {
+                            Ordering::Equal => Some(vec![&node]),
+                            // The node at an earlier position is combined when expanded there
+                            Ordering::Less => expanded
+                                .get(t)
+                                .map(|e| e.iter().filter(|(c, _, _)| !Arc::ptr_eq(c, &node.0)).collect()),
+                            Ordering::Greater => expanded.get(t).map(|e| e.iter().collect()),
+                        })
+                        .collect();
+
+                    let Some(args) = args else {
+                        continue;
+                    };
+
+                    for params in args.iter().map(|a| a.iter()).multi_cartesian_product() {
+                        if config.exhausted(evaluations.load(std::sync::atomic::Ordering::Relaxed)) {
+                            exhausted.store(true, std::sync::atomic::Ordering::Relaxed);
+                            return (ranking.into_sorted(), types);
+                        }
+
+                        progress.evaluated(evaluations.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+
+                        let values: Vec<NeuronValue> = params.iter().map(|(_, v, _)| v.clone()).collect();
+                        let inputs: Vec<ConnectionValue> = params
+                            .iter()
+                            .map(|(c, _, _)| ConnectionValue::Connection(Arc::clone(c)))
+                            .collect();
+
+                        let Some(output) = config.apply(neuron, &inputs, &values) else {
+                            continue;
+                        };
+
+                        push(Arc::new(Connection::new(Arc::clone(neuron), &inputs)), output, &mut frontier, &mut seen);
+                    }
+                }
+            }
+        }
+
+        (ranking.into_sorted(), types)
+    }
+
+    fn search_sketches(
+        &self,
+        targets: &[NeuronValue],
+        target_types: &[ValueType],
+        config: &SearchConfig,
+        progress: &Progress,
+    ) -> Search {
+        let mut evaluations = 0;
+        let (bank, exhausted) = self.closed_connections(config.max_level, config, progress, &mut evaluations);
+        let mut exhausted = exhausted || config.exhausted(evaluations);
+        let evaluations = AtomicUsize::new(evaluations);
+        let stopped = AtomicBool::new(false);
+        let mut missing_parameters: HashMap<ValueType, ValueType> = HashMap::new();
+        let mut sketch_args: Vec<(&Arc<Connection>, Vec<&Vec<Closed> >)> = Vec::new();
+
+        'sketches: for sketch in &config.sketches {
+            let mut args = Vec::new();
+
+            for hole in sketch.holes() {
+                match bank.get(&hole) {
+                    Some(entries) => args.push(entries),
+                    None => {
+                        missing_parameters
+                            .entry(sketch.neuron().output_type().clone())
+                            .or_insert(hole);
+
+                        continue 'sketches;
+                    }
+                }
+            }
+
+            sketch_args.push((sketch, args));
+        }
+
+        let ranked: Vec<Vec<Pair> > = targets
+            .par_iter()
+            .map(|target| {
+                let ranking = Ranking::new(config.top_k.max(1), progress);
+
+                for (sketch, args) in &sketch_args {
+                    args.iter()
+                        .map(|entries| entries.iter())
+                        .multi_cartesian_product()
+                        .take_while(|_| {
+                            if ranking.threshold() < config.eps {
+                                return false;
+                            }
+
+                            // The fills share the budget of the bank, so a bank exhausting it leaves none
+                            if config.exhausted(evaluations.load(std::sync::atomic::Ordering::Relaxed)) {
+                                stopped.store(true, std::sync::atomic::Ordering::Relaxed);
+                                return false;
+                            }
+
+                            true
+                        })
+                        .par_bridge()
+                        .for_each(|params| {
+                            progress.evaluated(evaluations.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+
+                            let values: Vec<ConnectionValue> = params
+                                .iter()
+                                .map(|(c, _, _)| ConnectionValue::Connection(Arc::clone(c)))
+                                .collect();
+                            let conn = Arc::new(sketch.fill_holes(&values));
+                            let cost = config
+                                .output(&conn)
+                                .map(|v| config.heuristic(&v, target))
+                                .unwrap_or(f64::INFINITY);
+
+                            ranking.insert(cost, || conn);
+                        });
+                }
+
+                ranking.into_sorted()
+            })
+            .collect();
+
+        exhausted |= stopped.load(std::sync::atomic::Ordering::Relaxed);
+
+        let candidate_types: HashSet<ValueType> = sketch_args
+            .iter()
+            .map(|(sketch, _)| sketch.neuron().output_type().clone())
+            .collect();
+
+        let connections = target_types
+            .iter()
+            .zip(&ranked)
+            .map(|(target_type, pairs)| {
+                pairs
+                    .first()
+                    .map(|pair| pair.connection.clone())
+                    .ok_or_else(|| search_error(target_type.clone(), exhausted, &candidate_types, &missing_parameters))
+            })
+            .collect();
+
+        Search {
+            connections,
+            ranked,
+            evaluations: evaluations.into_inner(),
+            exhausted,
+            stats: SearchStats::default(),
+        }
+    }
+
+    /// Closed connections of depth up to `levels` built bottom-up, by output type,
+    /// keeping the cheapest connection per distinct output value
+    fn closed_connections(
+        &self,
+        levels: usize,
+        config: &SearchConfig,
+        progress: &Progress,
+        evaluations: &mut usize,
+    ) -> (HashMap<ValueType, Vec<Closed> >, bool) {
+        let mut exhausted = false;
+        let mut seen: HashSet<NeuronValue> = HashSet::new();
+        let mut bank: HashMap<ValueType, Vec<Closed> > = HashMap::new();
+
+        for neuron in &self.neurons {
+            if neuron.input_types().is_empty() {
+                let conn = Arc::new(Connection::new(Arc::clone(neuron), &[]));
+
+                *evaluations += 1;
+                progress.evaluated(*evaluations);
+
+                if let Some(value) = config.output(&conn) {
+                    if seen.insert(value.clone()) {
+                        bank.entry(neuron.output_type().clone()).or_default().push((conn, value, 0));
+                    }
+                }
+            }
+        }
+
+        progress.generated(0, bank.values().map(Vec::len).sum());
+        progress.level(0, *evaluations);
+
+        for level in 0..levels {
+            let mut level_values: HashMap<NeuronValue, (Arc<Connection>, ValueType)> = HashMap::new();
+
+            for neuron in &self.neurons {
+                let input_types = neuron.input_types();
+
+                if input_types.is_empty() {
+                    continue;
+                }
+
+                if config.exhausted(*evaluations) {
+                    exhausted = true;
+                    break;
+                }
+
+                let args: Option<Vec<&Vec<Closed> > > = input_types
+                    .iter()
+                    .map(|t| bank.get(t))
+                    .collect();
+
+                let Some(args) = args else {
+                    continue;
+                };
+
+                let count = AtomicUsize::new(0);
+                let base = *evaluations;
+
+                let outputs: Vec<(NeuronValue, Arc<Connection>)> = args
+                    .iter()
+                    .map(|entries| entries.iter())
+                    .multi_cartesian_product()
+                    .filter(|params| params.iter().any(|(_, _, l)| *l == level))
+                    .take_while(|_| !config.exhausted(*evaluations + count.load(std::sync::atomic::Ordering::Relaxed)))
+                    .par_bridge()
+                    .filter_map(|params| {
+                        progress.evaluated(base + count.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+
+                        let values: Vec<NeuronValue> = params.iter().map(|(_, v, _)| v.clone()).collect();
+                        let inputs: Vec<ConnectionValue> = params
+                            .iter()
+                            .map(|(c, _, _)| ConnectionValue::Connection(Arc::clone(c)))
+                            .collect();
+                        let value = config.apply(neuron, &inputs, &values)?;
+
+                        if seen.contains(&value) {
+                            return None;
+                        }
+
+                        Some((value, Arc::new(Connection::new(Arc::clone(neuron), &inputs))))
+                    })
+                    .collect();
+
+                *evaluations += count.into_inner();
+
+                for (value, conn) in outputs {
+                    match level_values.get(&value) {
+                        Some((existing, _)) if !cheaper(&conn, existing) => {}
+                        _ => {
+                            level_values.insert(value, (conn, neuron.output_type().clone()));
+                        }
+                    }
+                }
+            }
+
+            if config.exhausted(*evaluations) {
+                exhausted = true;
+            }
+
+            progress.generated(level + 1, level_values.len());
+
+            for (value, (conn, output_type)) in level_values {
+                seen.insert(value.clone());
+                bank.entry(output_type).or_default().push((conn, value, level + 1));
+            }
+
+            progress.level(level + 1, *evaluations);
+
+            if exhausted {
+                break;
+            }
+        }
+
+        (bank, exhausted)
+    }
+
+    /// First input type without any candidate connection, by output type of the neurons needing it
+    fn missing_parameters(&self, candidate_types: &HashSet<ValueType>) -> HashMap<ValueType, ValueType> {
+        let mut missing_parameters: HashMap<ValueType, ValueType> = HashMap::new();
+
+        for neuron in &self.neurons {
+            if let Some(input_type) = neuron.input_types().iter().find(|t| !candidate_types.contains(t)) {
+                missing_parameters
+                    .entry(neuron.output_type().clone())
+                    .or_insert(input_type.clone());
+            }
+        }
+
+        missing_parameters
     }
 }
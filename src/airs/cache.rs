@@ -0,0 +1,162 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use super::connection::{Connection, ConnectionValue};
+use super::neuron::NeuronValue;
+
+/// Counts of the lookups of an `OutputCache` and its current size
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+impl CacheStats {
+    /// Share of the lookups found in the cache, 0 without lookups
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+/// Output of a connection with its holes bound to `inputs` in the context of the given hash; the
+/// connection is kept to tell apart colliding keys and so that the neurons it refers to by address
+/// outlive the entry
+#[derive(Debug)]
+struct Entry {
+    connection: Arc<Connection>,
+    inputs: Vec<ConnectionValue>,
+    context: u64,
+    output: Option<NeuronValue>,
+    bytes: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<u64, Entry>,
+    order: VecDeque<u64>,
+    bytes: usize,
+}
+
+/// Thread-safe memo of the outputs of connections, keyed on their structural hash, the inputs bound to
+/// their holes and the hash of the evaluation context; the oldest entries are evicted once their approximate size exceeds `max_bytes`
+#[derive(Debug)]
+pub struct OutputCache {
+    max_bytes: usize,
+    entries: RwLock<Entries>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+}
+
+impl OutputCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            entries: RwLock::new(Entries::default()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            evictions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Cached output of `connection` with its holes bound to `inputs` in the context hashed as `context`,
+    /// or `output()` once stored
+    pub fn get_or_insert_with(
+        &self,
+        connection: &Arc<Connection>,
+        inputs: &[ConnectionValue],
+        context: u64,
+        output: impl FnOnce() -> Option<NeuronValue>,
+    ) -> Option<NeuronValue> {
+        let mut hasher = DefaultHasher::new();
+
+        connection.hash(&mut hasher);
+        inputs.hash(&mut hasher);
+        context.hash(&mut hasher);
+
+        let key = hasher.finish();
+
+        if let Some(entry) = self.entries.read().expect("Lock poisoned").map.get(&key) {
+            if entry.context == context && entry.connection == *connection && entry.inputs == inputs {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return entry.output.clone();
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let value = output();
+        let bytes = std::mem::size_of::<Entry>()
+            + value.as_ref().map_or(0, NeuronValue::size)
+            + inputs
+                .iter()
+                .map(|input| match input {
+                    ConnectionValue::Value(v) => v.size(),
+                    ConnectionValue::Connection(_) => std::mem::size_of::<ConnectionValue>(),
+                })
+                .sum::<usize>();
+
+        if bytes <= self.max_bytes {
+            self.insert(key, Entry {
+                connection: Arc::clone(connection),
+                inputs: inputs.to_vec(),
+                context,
+                output: value.clone(),
+                bytes,
+            });
+        }
+
+        value
+    }
+
+    fn insert(&self, key: u64, entry: Entry) {
+        let mut entries = self.entries.write().expect("Lock poisoned");
+
+        entries.bytes += entry.bytes;
+
+        if let Some(replaced) = entries.map.insert(key, entry) {
+            entries.bytes -= replaced.bytes;
+        } else {
+            entries.order.push_back(key);
+        }
+
+        while entries.bytes > self.max_bytes {
+            let Some(oldest) = entries.order.pop_front() else {
+                break;
+            };
+
+            if let Some(evicted) = entries.map.remove(&oldest) {
+                entries.bytes -= evicted.bytes;
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.read().expect("Lock poisoned");
+
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: entries.map.len(),
+            bytes: entries.bytes,
+        }
+    }
+
+    /// Remove every entry, keeping the counts of the lookups
+    #[cfg(test)]
+    pub fn clear(&self) {
+        let mut entries = self.entries.write().expect("Lock poisoned");
+
+        *entries = Entries::default();
+    }
+}
@@ -2,17 +2,23 @@ use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::sync::{Arc, RwLock};
 
+use super::cache::OutputCache;
+use super::context::Context;
 use super::neuron::Neuron;
 use super::neuron::ValueType;
 use super::neuron::NeuronValue;
+use super::parser::literal_string;
 use super::utility::*;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum ConnectionValue {
     Value(NeuronValue),
     Connection(Arc<Connection>),
 }
 
+/// Cache memoizing the outputs of sub-connections and the hash of the context they are evaluated in
+type Memo<'a> = Option<(&'a OutputCache, u64)>;
+
 #[derive(Debug)]
 pub struct Connection {
     neuron: Arc<Neuron>,
@@ -77,25 +83,7 @@ impl Connection {
 
     pub fn to_string(&self) -> String {
         let args: Vec<String> = self.inputs.read().expect("Lock poisoned").iter().map(|v| match v {
-            ConnectionValue::Value(value) => match value {
-                NeuronValue::Bool(b) => b.to_string(),
-                NeuronValue::Char(s) => s.clone(),
-                NeuronValue::Int8(i) => i.to_string(),
-                NeuronValue::Int16(i) => i.to_string(),
-                NeuronValue::Int32(i) => i.to_string(),
-                NeuronValue::Int64(i) => i.to_string(),
-                NeuronValue::Float(f) => f.to_string(),
-                NeuronValue::Double(d) => d.to_string(),
-                NeuronValue::Grid(g) => format!("{:#?}", g),
-                NeuronValue::Grids(g) => format!("{:#?}", g),
-                NeuronValue::String(s) => s.clone(),
-                NeuronValue::ValueType(t) => format!("{:?}", t),
-                NeuronValue::PairGrids(v) => format!("{:#?}", v),
-                NeuronValue::Map(m) => format!("{:#?}", m),
-                NeuronValue::LocationPairs(p) => format!("{:#?}", p),
-                NeuronValue::RegionsList(r) => format!("{:#?}", r),
-            }
-
+            ConnectionValue::Value(value) => literal_string(value),
             ConnectionValue::Connection(c) => c.to_string(),
         }).collect();
 
@@ -143,13 +131,35 @@ impl Connection {
     }
 
     pub fn output(&self) -> Option<NeuronValue> {
-        let mut args = Vec::with_capacity(self.inputs.read().expect("Lock poisoned").len());
+        self.output_in(&Context::new())
+    }
+
+    /// Output with the constant neurons named in `context` outputting their bound values, so that a
+    /// program can be evaluated on several examples at once without rewriting its neurons
+    pub fn output_in(&self, context: &Context) -> Option<NeuronValue> {
+        self.evaluate(context, None)
+    }
+
+    /// Same as `output_in`, the outputs of the sub-connections being memoized in `cache`
+    pub fn output_cached(&self, context: &Context, cache: &OutputCache) -> Option<NeuronValue> {
+        self.evaluate(context, Some((cache, context.key())))
+    }
+
+    fn evaluate(&self, context: &Context, memo: Memo) -> Option<NeuronValue> {
         let inputs = self.inputs.read().expect("Lock poisoned");
 
+        if inputs.is_empty() && !context.is_empty() {
+            if let Some(value) = context.get(&self.neuron.name()) {
+                return Some(value.clone());
+            }
+        }
+
+        let mut args = Vec::with_capacity(inputs.len());
+
         for v in inputs.iter() {
             match v {
                 ConnectionValue::Connection(inner) => {
-                    args.push(inner.output()?);
+                    args.push(memoized(inner, context, memo)?);
                 }
                 ConnectionValue::Value(value) => args.push(value.clone()),
             }
@@ -232,7 +242,7 @@ impl Connection {
                 ConnectionValue::Value(value) => {
                     match value {
                         NeuronValue::ValueType(t) => {
-                            types.push(t.clone());
+                            types.push(t.normalized());
                         }
 
                         other => {
@@ -260,7 +270,68 @@ impl Connection {
         Connection::new(self.neuron.clone(), &new_inputs)
     }
 
+    /// Types of the `ValueType` holes of a sketch, in the order `fill_holes` fills them
+    pub fn holes(&self) -> Vec<ValueType> {
+        let inputs = self.inputs.read().expect("Lock poisoned");
+        let mut holes = Vec::new();
+
+        for input in inputs.iter() {
+            match input {
+                ConnectionValue::Connection(conn) => holes.extend(conn.holes()),
+                ConnectionValue::Value(NeuronValue::ValueType(t)) => holes.push(t.normalized()),
+                ConnectionValue::Value(_) => {}
+            }
+        }
+
+        holes
+    }
+
+    /// Copy of a sketch with its holes replaced by `values`, the rest of the connection being kept
+    pub fn fill_holes(&self, values: &[ConnectionValue]) -> Connection {
+        debug_assert_eq!(values.len(), self.holes().len());
+
+        let filled = self.deep_clone();
+        let mut index = 0;
+
+        filled.apply_inputs(&self.hole_inputs(values, &mut index));
+        filled
+    }
+
+    /// Inputs of `apply_inputs` keeping every input of the connection but the holes
+    fn hole_inputs(&self, values: &[ConnectionValue], index: &mut usize) -> Vec<ConnectionValue> {
+        let inputs = self.inputs.read().expect("Lock poisoned");
+        let mut hole_inputs = Vec::with_capacity(inputs.len());
+
+        for input in inputs.iter() {
+            match input {
+                ConnectionValue::Connection(conn) if conn.input_types().is_empty() => {
+                    hole_inputs.push(input.clone());
+                }
+                ConnectionValue::Connection(conn) => {
+                    hole_inputs.extend(conn.hole_inputs(values, index));
+                }
+                ConnectionValue::Value(NeuronValue::ValueType(_)) => {
+                    hole_inputs.push(values[*index].clone());
+                    *index += 1;
+                }
+                ConnectionValue::Value(_) => hole_inputs.push(input.clone()),
+            }
+        }
+
+        hole_inputs
+    }
+
     pub fn output_with_inputs(&self, inputs: &[ConnectionValue]) -> Option<NeuronValue> {
+        self.evaluate_with_inputs(inputs, None)
+    }
+
+    /// Same as `output_with_inputs`, the outputs of the sub-connections with their slice of `inputs` and of
+    /// the connections given as inputs being memoized in `cache`
+    pub fn output_with_inputs_cached(&self, inputs: &[ConnectionValue], cache: &OutputCache) -> Option<NeuronValue> {
+        self.evaluate_with_inputs(inputs, Some((cache, Context::new().key())))
+    }
+
+    fn evaluate_with_inputs(&self, inputs: &[ConnectionValue], memo: Memo) -> Option<NeuronValue> {
         let self_inputs = self.inputs.read().expect("Lock poisoned");
         let mut args: Vec<NeuronValue> = Vec::with_capacity(self_inputs.len());
         let mut index = 0;
@@ -274,15 +345,21 @@ impl Connection {
                         return None;
                     }
 
-                    let value = conn.output_with_inputs(&inputs[index..index + size])?;
+                    let bound = &inputs[index..index + size];
+                    let value = match memo {
+                        Some((cache, key)) => {
+                            cache.get_or_insert_with(conn, bound, key, || conn.evaluate_with_inputs(bound, memo))
+                        }
+                        None => conn.evaluate_with_inputs(bound, memo),
+                    }?;
                     args.push(value);
                     index += size;
                 }
 
-                ConnectionValue::Value(v) => {
+                ConnectionValue::Value(_) => {
                     if index < inputs.len() {
                         match inputs[index].clone() {
-                            ConnectionValue::Connection(c) => args.push(c.output().unwrap()),
+                            ConnectionValue::Connection(c) => args.push(memoized(&c, &Context::new(), memo)?),
                             ConnectionValue::Value(v) => args.push(v),
                         }
 
@@ -299,3 +376,11 @@ impl Connection {
         function(&args)
     }
 }
+
+/// Output of `connection` in `context`, looked up in the cache if any
+fn memoized(connection: &Arc<Connection>, context: &Context, memo: Memo) -> Option<NeuronValue> {
+    match memo {
+        Some((cache, key)) => cache.get_or_insert_with(connection, &[], key, || connection.evaluate(context, memo)),
+        None => connection.evaluate(context, memo),
+    }
+}
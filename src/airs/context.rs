@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use super::neuron::NeuronValue;
+
+/// Values bound to the names of constant neurons for one evaluation, such as the `input` of an example;
+/// a bound neuron outputs its value instead of applying its function
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Context {
+    values: HashMap<String, NeuronValue>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: NeuronValue) -> Self {
+        self.bind(name, value);
+        self
+    }
+
+    pub fn bind(&mut self, name: impl Into<String>, value: NeuronValue) {
+        self.values.insert(name.into(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NeuronValue> {
+        self.values.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Hash of the bound values, identifying the context in an `OutputCache`
+    pub fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Hash for Context {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut names: Vec<&String> = self.values.keys().collect();
+
+        names.sort();
+
+        for name in names {
+            name.hash(state);
+            self.values[name].hash(state);
+        }
+    }
+}
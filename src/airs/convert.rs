@@ -0,0 +1,192 @@
+use ndarray::Array2;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::neuron::{Neuron, NeuronValue, ValueType};
+
+/// Rust type carried by a `NeuronValue` variant
+pub trait NeuronType: Sized {
+    fn value_type() -> ValueType;
+    fn from_value(value: &NeuronValue) -> Option<Self>;
+    fn into_value(self) -> NeuronValue;
+
+    /// `from_value` moving out of the value instead of cloning it
+    fn from_owned(value: NeuronValue) -> Option<Self> {
+        Self::from_value(&value)
+    }
+}
+
+macro_rules! impl_neuron_type {
+    ($ty:ty, $variant:ident) => {
+        impl NeuronType for $ty {
+            fn value_type() -> ValueType {
+                ValueType::$variant
+            }
+
+            fn from_value(value: &NeuronValue) -> Option<Self> {
+                match value {
+                    NeuronValue::$variant(v) => Some(v.clone()),
+                    _ => None,
+                }
+            }
+
+            fn into_value(self) -> NeuronValue {
+                NeuronValue::$variant(self)
+            }
+
+            fn from_owned(value: NeuronValue) -> Option<Self> {
+                match value {
+                    NeuronValue::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_neuron_type!(bool, Bool);
+impl_neuron_type!(f64, Double);
+impl_neuron_type!(f32, Float);
+impl_neuron_type!(i8, Int8);
+impl_neuron_type!(i16, Int16);
+impl_neuron_type!(i32, Int32);
+impl_neuron_type!(i64, Int64);
+impl_neuron_type!(u8, UInt8);
+impl_neuron_type!(u16, UInt16);
+impl_neuron_type!(u32, UInt32);
+impl_neuron_type!(u64, UInt64);
+impl_neuron_type!(usize, Index);
+impl_neuron_type!(String, String);
+impl_neuron_type!(Array2<i8>, Grid);
+impl_neuron_type!(HashMap<i8, i8>, Map);
+impl_neuron_type!(Vec<Vec<((isize, isize), (isize, isize))> >, LocationPairs);
+impl_neuron_type!(Vec<Vec<Vec<(isize, isize)> > >, RegionsList);
+
+/// `List` of the item type, `Vec<Array2<i8>>` being `Grids` and `Vec<(Array2<i8>, Array2<i8>)>` `PairGrids`
+impl<T: NeuronType> NeuronType for Vec<T> {
+    fn value_type() -> ValueType {
+        ValueType::list(T::value_type())
+    }
+
+    fn from_value(value: &NeuronValue) -> Option<Self> {
+        value.items()?.into_iter().map(T::from_owned).collect()
+    }
+
+    fn into_value(self) -> NeuronValue {
+        NeuronValue::list(T::value_type(), self.into_iter().map(T::into_value).collect())
+    }
+}
+
+macro_rules! impl_tuple_neuron_type {
+    ($($item:ident),*) => {
+        impl<$($item: NeuronType),*> NeuronType for ($($item,)*) {
+            fn value_type() -> ValueType {
+                ValueType::Tuple(vec![$($item::value_type()),*])
+            }
+
+            #[allow(non_snake_case)]
+            fn from_value(value: &NeuronValue) -> Option<Self> {
+                let NeuronValue::Tuple(items) = value else {
+                    return None;
+                };
+                let [$($item),*] = items.as_slice() else {
+                    return None;
+                };
+
+                Some(($($item::from_value($item)?,)*))
+            }
+
+            #[allow(non_snake_case)]
+            fn into_value(self) -> NeuronValue {
+                let ($($item,)*) = self;
+
+                NeuronValue::Tuple(vec![$($item.into_value()),*])
+            }
+        }
+    };
+}
+
+impl_tuple_neuron_type!(A, B);
+impl_tuple_neuron_type!(A, B, C);
+
+/// Return value of a typed neuron function, `None` meaning the neuron doesn't apply
+pub trait NeuronOutput {
+    fn value_type() -> ValueType;
+    fn into_output(self) -> Option<NeuronValue>;
+}
+
+impl<T: NeuronType> NeuronOutput for T {
+    fn value_type() -> ValueType {
+        T::value_type()
+    }
+
+    fn into_output(self) -> Option<NeuronValue> {
+        Some(self.into_value())
+    }
+}
+
+impl<T: NeuronType> NeuronOutput for Option<T> {
+    fn value_type() -> ValueType {
+        T::value_type()
+    }
+
+    fn into_output(self) -> Option<NeuronValue> {
+        self.map(T::into_value)
+    }
+}
+
+/// Typed Rust function usable as a neuron function, `Args` being the tuple of its argument types
+pub trait IntoNeuronFn<Args>: Send + Sync + 'static {
+    fn input_types() -> Vec<ValueType>;
+    fn output_type() -> ValueType;
+    fn call(&self, inputs: &[NeuronValue]) -> Option<NeuronValue>;
+}
+
+macro_rules! impl_into_neuron_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> IntoNeuronFn<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync + 'static,
+            R: NeuronOutput,
+            $($arg: NeuronType),*
+        {
+            fn input_types() -> Vec<ValueType> {
+                vec![$($arg::value_type()),*]
+            }
+
+            fn output_type() -> ValueType {
+                R::value_type()
+            }
+
+            #[allow(non_snake_case)]
+            fn call(&self, inputs: &[NeuronValue]) -> Option<NeuronValue> {
+                let [$($arg),*] = inputs else {
+                    return None;
+                };
+
+                self($($arg::from_value($arg)?),*).into_output()
+            }
+        }
+    };
+}
+
+impl_into_neuron_fn!();
+impl_into_neuron_fn!(A);
+impl_into_neuron_fn!(A, B);
+impl_into_neuron_fn!(A, B, C);
+impl_into_neuron_fn!(A, B, C, D);
+impl_into_neuron_fn!(A, B, C, D, E);
+impl_into_neuron_fn!(A, B, C, D, E, G);
+
+impl Neuron {
+    /// Neuron of a typed function, its input and output types and the conversions
+    /// from and to `NeuronValue` being derived from the signature
+    pub fn from_fn<Args, F: IntoNeuronFn<Args> >(name: impl Into<String>, f: F) -> Self {
+        Self::new(
+            name,
+            RwLock::new(Arc::new(move |inputs: &[NeuronValue]| f.call(inputs))),
+            F::input_types(),
+            F::output_type(),
+        )
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::brain::{validate, Brain, Pair, Search, SearchConfig};
+use super::connection::{Connection, ConnectionValue};
+use super::context::Context;
+use super::neuron::{Neuron, NeuronFn, NeuronValue, ValueType};
+
+/// Values bound to the input neurons on one example of a task, and the output expected from a program
+#[derive(Clone, Debug, PartialEq)]
+pub struct Example {
+    pub context: Context,
+    pub output: NeuronValue,
+}
+
+impl Example {
+    pub fn new(context: Context, output: NeuronValue) -> Self {
+        Self { context, output }
+    }
+}
+
+/// Values of a connection on every example, as a list of the value on each example; the list is built
+/// without normalization so that lists of grids keep one item per example
+fn per_example(item_type: &ValueType, values: Vec<NeuronValue>) -> NeuronValue {
+    NeuronValue::List(item_type.clone(), values)
+}
+
+/// Neurons outputting the values of the original neurons on every example, literal values being lifted
+/// to constant neurons outputting them on every example
+struct Lifter<'a> {
+    examples: &'a [Example],
+    lifted: HashMap<*const Neuron, Arc<Neuron> >,
+    originals: HashMap<*const Neuron, Arc<Neuron> >,
+    literals: HashMap<*const Neuron, NeuronValue>,
+}
+
+impl<'a> Lifter<'a> {
+    fn new(examples: &'a [Example]) -> Self {
+        Self {
+            examples,
+            lifted: HashMap::new(),
+            originals: HashMap::new(),
+            literals: HashMap::new(),
+        }
+    }
+
+    /// Constant neurons output their value bound by each example, or their own value if unbound;
+    /// the other neurons are applied to the values of their inputs on each example
+    fn neuron(&mut self, neuron: &Arc<Neuron>) -> Arc<Neuron> {
+        if let Some(lifted) = self.lifted.get(&Arc::as_ptr(neuron)) {
+            return Arc::clone(lifted);
+        }
+
+        let name = neuron.name();
+        let output_type = neuron.output_type().clone();
+        let function = Arc::clone(&neuron.function.read().expect("Lock poisoned"));
+
+        let lifted_function: Arc<NeuronFn> = if neuron.input_types().is_empty() {
+            let values: Option<Vec<NeuronValue> > = self
+                .examples
+                .iter()
+                .map(|e| e.context.get(&name).cloned().or_else(|| function(&[])))
+                .collect();
+
+            Arc::new(move |_inputs: &[NeuronValue]| Some(per_example(&output_type, values.clone()?)))
+        } else {
+            let n = self.examples.len();
+
+            Arc::new(move |inputs: &[NeuronValue]| {
+                let columns: Vec<&Vec<NeuronValue> > = inputs
+                    .iter()
+                    .map(|input| match input {
+                        NeuronValue::List(_, values) if values.len() == n => Some(values),
+                        _ => None,
+                    })
+                    .collect::<Option<_> >()?;
+                let outputs: Vec<NeuronValue> = (0..n)
+                    .map(|i| function(&columns.iter().map(|c| c[i].clone()).collect::<Vec<_> >()))
+                    .collect::<Option<_> >()?;
+
+                Some(per_example(&output_type, outputs))
+            })
+        };
+
+        let lifted = Arc::new(Neuron::new(
+            name,
+            RwLock::new(lifted_function),
+            neuron.input_types().to_vec(),
+            neuron.output_type().clone(),
+        ));
+
+        self.lifted.insert(Arc::as_ptr(neuron), Arc::clone(&lifted));
+        self.originals.insert(Arc::as_ptr(&lifted), Arc::clone(neuron));
+
+        lifted
+    }
+
+    /// Constant neuron outputting `value` on every example
+    fn literal(&mut self, value: &NeuronValue) -> Arc<Neuron> {
+        let values = vec![value.clone(); self.examples.len()];
+        let output_type = value.value_type();
+        let item_type = output_type.clone();
+        let literal = Arc::new(Neuron::new(
+            value.to_string(),
+            RwLock::new(Arc::new(move |_inputs: &[NeuronValue]| Some(per_example(&item_type, values.clone())))),
+            vec![],
+            output_type,
+        ));
+
+        self.literals.insert(Arc::as_ptr(&literal), value.clone());
+
+        literal
+    }
+
+    fn connection(&mut self, connection: &Connection) -> Connection {
+        let inputs: Vec<ConnectionValue> = connection
+            .inputs()
+            .iter()
+            .map(|input| match input {
+                ConnectionValue::Connection(c) => ConnectionValue::Connection(Arc::new(self.connection(c))),
+                ConnectionValue::Value(NeuronValue::ValueType(_)) => input.clone(),
+                ConnectionValue::Value(v) => {
+                    ConnectionValue::Connection(Arc::new(Connection::new(self.literal(v), &[])))
+                }
+            })
+            .collect();
+
+        Connection::new(self.neuron(&connection.neuron()), &inputs)
+    }
+
+    fn original(&self, connection: &Connection) -> Connection {
+        let inputs: Vec<ConnectionValue> = connection
+            .inputs()
+            .iter()
+            .map(|input| match input {
+                ConnectionValue::Connection(c) => match self.literals.get(&Arc::as_ptr(&c.neuron())) {
+                    Some(value) => ConnectionValue::Value(value.clone()),
+                    None => ConnectionValue::Connection(Arc::new(self.original(c))),
+                },
+                ConnectionValue::Value(_) => input.clone(),
+            })
+            .collect();
+        let neuron = connection.neuron();
+
+        Connection::new(Arc::clone(self.originals.get(&Arc::as_ptr(&neuron)).unwrap_or(&neuron)), &inputs)
+    }
+}
+
+impl Brain {
+    /// Search a connection mapping the inputs bound by each example to its output, a candidate being
+    /// evaluated on every example and its cost combining the costs of each; neurons thus work on the
+    /// values of a single example, such as one `Grid`
+    pub fn search_examples(&self, examples: &[Example], config: &SearchConfig) -> Search {
+        assert!(!examples.is_empty(), "No example to search");
+
+        let outputs: Vec<NeuronValue> = examples.iter().map(|e| e.output.clone()).collect();
+        let output_type = outputs[0].value_type();
+        let target = per_example(&output_type, outputs.clone());
+        let mut lifter = Lifter::new(examples);
+
+        let neurons: Vec<Arc<Neuron> > = self
+            .instantiated(&outputs, config)
+            .iter()
+            .map(|n| lifter.neuron(n))
+            .collect();
+        let mut lifted_config = config.clone();
+
+        // Lifted connections are only evaluated on the examples, the folds are validated once lowered
+        lifted_config.sketches = config.sketches.iter().map(|s| Arc::new(lifter.connection(s))).collect();
+        lifted_config.holdout.clear();
+
+        let search = Brain::new(neurons).search_typed(&[target], &[output_type], &lifted_config);
+        let mut search = Search {
+            connections: search
+                .connections
+                .into_iter()
+                .map(|c| c.map(|c| Arc::new(lifter.original(&c))))
+                .collect(),
+            ranked: search
+                .ranked
+                .into_iter()
+                .map(|pairs| {
+                    pairs
+                        .into_iter()
+                        .map(|pair| Pair {
+                            connection: Arc::new(lifter.original(&pair.connection)),
+                            ..pair
+                        })
+                        .collect()
+                })
+                .collect(),
+            evaluations: search.evaluations,
+            exhausted: search.exhausted,
+            stats: search.stats,
+        };
+
+        validate(&mut search, config);
+        search
+    }
+}
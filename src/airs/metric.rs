@@ -0,0 +1,172 @@
+use itertools::Itertools;
+use ndarray::Array2;
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+use super::utility::*;
+
+/// Cost added when two grids or two lists can't be compared element-wise
+pub const MISSING_COST: f64 = 100.0;
+
+/// Distance between a grid and a target grid, 0 meaning equal
+pub trait GridMetric: Debug + Send + Sync {
+    fn distance(&self, val: &Array2<i8>, target: &Array2<i8>) -> f64;
+}
+
+/// Sum of the absolute differences of the colors, `MISSING_COST` plus the difference of the color
+/// sums for grids of different shapes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ColorDistance;
+
+impl GridMetric for ColorDistance {
+    fn distance(&self, val: &Array2<i8>, target: &Array2<i8>) -> f64 {
+        if val.shape() != target.shape() {
+            let sum = |g: &Array2<i8>| g.iter().map(|&v| v as i64).sum::<i64>();
+
+            return MISSING_COST + (sum(val) - sum(target)).abs() as f64;
+        }
+
+        val.iter()
+            .zip(target.iter())
+            .map(|(&a, &b)| (a as i64 - b as i64).abs())
+            .sum::<i64>() as f64
+    }
+}
+
+/// Number of cells of different colors, the cells outside the common area of grids of different
+/// shapes counting as mismatches
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CellMismatch;
+
+impl GridMetric for CellMismatch {
+    fn distance(&self, val: &Array2<i8>, target: &Array2<i8>) -> f64 {
+        let rows = val.nrows().min(target.nrows());
+        let cols = val.ncols().min(target.ncols());
+        let common = rows * cols;
+        let mismatches = (0..rows)
+            .flat_map(|i| (0..cols).map(move |j| (i, j)))
+            .filter(|&at| val[at] != target[at])
+            .count();
+
+        (mismatches + val.len() - common + target.len() - common) as f64
+    }
+}
+
+/// Sum over the colors of either grid of one minus the intersection over union of their cells
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ColorIoU;
+
+impl GridMetric for ColorIoU {
+    fn distance(&self, val: &Array2<i8>, target: &Array2<i8>) -> f64 {
+        let cells = |g: &Array2<i8>, color: i8| -> HashSet<(usize, usize)> {
+            g.indexed_iter().filter(|(_, &v)| v == color).map(|(at, _)| at).collect()
+        };
+        let colors: HashSet<i8> = val.iter().chain(target.iter()).copied().collect();
+
+        colors
+            .into_iter()
+            .map(|color| {
+                let a = cells(val, color);
+                let b = cells(target, color);
+
+                1.0 - a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+            })
+            .sum()
+    }
+}
+
+/// Edit distance between the rows of the grids, a row costing its length to insert or delete and the
+/// edit distance between its cells to substitute, so that a missing row or column costs its cells
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EditDistance;
+
+impl GridMetric for EditDistance {
+    fn distance(&self, val: &Array2<i8>, target: &Array2<i8>) -> f64 {
+        let rows = |g: &Array2<i8>| -> Vec<Vec<i8> > { g.rows().into_iter().map(|r| r.to_vec()).collect() };
+
+        edit_distance(&rows(val), &rows(target), |row| row.len() as f64, |a, b| {
+            edit_distance(a, b, |_| 1.0, |x, y| (x != y) as i32 as f64)
+        })
+    }
+}
+
+/// Objects, the 4-connected regions of a same color other than `background`, matched one-to-one; a
+/// matched pair costs 1 for a different color, the size of the symmetric difference of the shapes and
+/// the Manhattan distance between their top-left corners, an unmatched object costs 1 plus its size
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ObjectDistance {
+    pub background: i8,
+}
+
+/// Color, top-left corner and cells relative to it of an object
+type Object = (i8, (isize, isize), Vec<(isize, isize)>);
+
+impl ObjectDistance {
+    fn objects(&self, grid: &Array2<i8>) -> Vec<Object> {
+        region_set(grid, false)
+            .into_iter()
+            .filter(|r| grid[(r[0].0 as usize, r[0].1 as usize)] != self.background)
+            .map(|r| {
+                let color = grid[(r[0].0 as usize, r[0].1 as usize)];
+                let corner = (
+                    r.iter().map(|c| c.0).min().unwrap_or(0),
+                    r.iter().map(|c| c.1).min().unwrap_or(0),
+                );
+                let cells = r.iter().map(|c| (c.0 - corner.0, c.1 - corner.1)).sorted().collect();
+
+                (color, corner, cells)
+            })
+            .sorted()
+            .collect()
+    }
+}
+
+impl GridMetric for ObjectDistance {
+    fn distance(&self, val: &Array2<i8>, target: &Array2<i8>) -> f64 {
+        let shape = val.nrows().abs_diff(target.nrows()) + val.ncols().abs_diff(target.ncols());
+        let a = self.objects(val);
+        let b = self.objects(target);
+
+        if a == b {
+            return shape as f64;
+        }
+
+        let unmatched = |o: &Object| 1.0 + o.2.len() as f64;
+        let cost = |x: &Object, y: &Object| {
+            let x_cells: HashSet<&(isize, isize)> = x.2.iter().collect();
+            let y_cells: HashSet<&(isize, isize)> = y.2.iter().collect();
+
+            (x.0 != y.0) as i32 as f64
+                + x_cells.symmetric_difference(&y_cells).count() as f64
+                + (x.1.0.abs_diff(y.1.0) + x.1.1.abs_diff(y.1.1)) as f64
+        };
+
+        shape as f64 + matching_distance(&a, &b, cost, unmatched)
+    }
+}
+
+/// Levenshtein distance between sequences with weighted insertions, deletions and substitutions
+fn edit_distance<T>(a: &[T], b: &[T], indel: impl Fn(&T) -> f64, substitution: impl Fn(&T, &T) -> f64) -> f64 {
+    let mut previous: Vec<f64> = std::iter::once(0.0)
+        .chain(b.iter().scan(0.0, |acc, y| {
+            *acc += indel(y);
+            Some(*acc)
+        }))
+        .collect();
+
+    for x in a {
+        let mut current = vec![previous[0] + indel(x)];
+
+        for (j, y) in b.iter().enumerate() {
+            let cost = (previous[j] + substitution(x, y))
+                .min(previous[j + 1] + indel(x))
+                .min(current[j] + indel(y));
+
+            current.push(cost);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
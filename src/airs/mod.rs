@@ -1,13 +1,41 @@
 mod brain;
+mod cache;
 mod connection;
+mod context;
+mod convert;
+mod example;
+mod metric;
 mod neuron;
+mod parser;
+mod progress;
+mod registry;
 mod utility;
 
 pub use self::brain::Brain;
+pub use self::brain::CancellationToken;
+pub use self::brain::Search;
+pub use self::brain::SearchConfig;
+pub use self::brain::SearchError;
+pub use self::brain::Strategy;
+pub use self::cache::OutputCache;
 pub use self::connection::Connection;
 pub use self::connection::ConnectionValue;
+pub use self::context::Context;
+pub use self::example::Example;
+pub use self::metric::CellMismatch;
+pub use self::metric::ColorDistance;
+pub use self::metric::ColorIoU;
+pub use self::metric::EditDistance;
+pub use self::metric::GridMetric;
+pub use self::metric::ObjectDistance;
 pub use self::neuron::Neuron;
-pub use self::neuron::NeuronFn;
 pub use self::neuron::NeuronValue;
+pub use self::neuron::TypeBindings;
 pub use self::neuron::ValueType;
+pub use self::parser::parse_connection;
+pub use self::parser::ParseError;
+pub use self::progress::SearchObserver;
+pub use self::progress::SearchStats;
+pub use self::registry::NeuronRegistry;
+pub use self::registry::RegistryError;
 pub use self::utility::*;
@@ -1,9 +1,11 @@
+use itertools::Itertools;
 use ndarray::Array2;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter, Result};
 use std::hash::{Hash, Hasher};
 use std::sync::{Arc, RwLock};
-use std::collections::HashMap;
 
+use super::metric::{ColorDistance, GridMetric, MISSING_COST};
 use super::utility::*;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -16,6 +18,11 @@ pub enum ValueType {
     Int16,
     Int32,
     Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Index,
     String,
     Grid,
     Grids,
@@ -24,6 +31,128 @@ pub enum ValueType {
     PairGrids,
     LocationPairs,
     RegionsList,
+    List(Box<ValueType>),
+    Tuple(Vec<ValueType>),
+    Option(Box<ValueType>),
+    /// Type variable of a polymorphic neuron signature, bound by unification
+    Var(String),
+}
+
+/// Types bound to the type variables of a signature
+pub type TypeBindings = HashMap<String, ValueType>;
+
+impl ValueType {
+    pub fn var(name: impl Into<String>) -> ValueType {
+        ValueType::Var(name.into())
+    }
+
+    /// Whether the type contains a type variable
+    pub fn is_polymorphic(&self) -> bool {
+        match self {
+            ValueType::Var(_) => true,
+            ValueType::List(item) | ValueType::Option(item) => item.is_polymorphic(),
+            ValueType::Tuple(items) => items.iter().any(ValueType::is_polymorphic),
+            _ => false,
+        }
+    }
+
+    fn occurs(&self, name: &str) -> bool {
+        match self {
+            ValueType::Var(var) => var == name,
+            ValueType::List(item) | ValueType::Option(item) => item.occurs(name),
+            ValueType::Tuple(items) => items.iter().any(|t| t.occurs(name)),
+            _ => false,
+        }
+    }
+
+    /// Type with its bound type variables replaced, normalized
+    pub fn substitute(&self, bindings: &TypeBindings) -> ValueType {
+        let substituted = match self {
+            ValueType::Var(name) => match bindings.get(name) {
+                Some(bound) => return bound.substitute(bindings),
+                None => self.clone(),
+            },
+            ValueType::List(item) => ValueType::List(Box::new(item.substitute(bindings))),
+            ValueType::Tuple(items) => ValueType::Tuple(items.iter().map(|t| t.substitute(bindings)).collect()),
+            ValueType::Option(item) => ValueType::Option(Box::new(item.substitute(bindings))),
+            other => other.clone(),
+        };
+
+        substituted.normalized()
+    }
+
+    /// Bind the type variables of both types so that they become compatible; on failure, `bindings`
+    /// may hold part of the bindings and should be discarded
+    pub fn unify(&self, other: &ValueType, bindings: &mut TypeBindings) -> bool {
+        let a = self.substitute(bindings);
+        let b = other.substitute(bindings);
+
+        match (&a, &b) {
+            (ValueType::Var(x), ValueType::Var(y)) if x == y => true,
+            (ValueType::Var(name), t) | (t, ValueType::Var(name)) => {
+                if t.occurs(name) {
+                    return false;
+                }
+
+                bindings.insert(name.clone(), t.clone());
+                true
+            }
+            (ValueType::List(x), ValueType::List(y)) | (ValueType::Option(x), ValueType::Option(y)) => {
+                x.unify(y, bindings)
+            }
+            (ValueType::Tuple(xs), ValueType::Tuple(ys)) => {
+                xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| x.unify(y, bindings))
+            }
+            (ValueType::List(_), ValueType::Grids | ValueType::PairGrids)
+            | (ValueType::Grids | ValueType::PairGrids, ValueType::List(_)) => match (a.item_type(), b.item_type()) {
+                (Some(x), Some(y)) => x.unify(&y, bindings),
+                _ => false,
+            },
+            _ => a == b,
+        }
+    }
+
+    /// List of `item`, normalized
+    pub fn list(item: ValueType) -> ValueType {
+        ValueType::List(Box::new(item)).normalized()
+    }
+
+    /// Same type with the lists of grids and of grid pairs written as `Grids` and `PairGrids`,
+    /// the form used by neurons and connections
+    pub fn normalized(&self) -> ValueType {
+        match self {
+            ValueType::List(item) => match item.normalized() {
+                ValueType::Grid => ValueType::Grids,
+                ValueType::Tuple(items) if items == [ValueType::Grid, ValueType::Grid] => ValueType::PairGrids,
+                item => ValueType::List(Box::new(item)),
+            },
+            ValueType::Tuple(items) => ValueType::Tuple(items.iter().map(ValueType::normalized).collect()),
+            ValueType::Option(item) => ValueType::Option(Box::new(item.normalized())),
+            other => other.clone(),
+        }
+    }
+
+    /// Type of the items of a list type
+    pub fn item_type(&self) -> Option<ValueType> {
+        match self.normalized() {
+            ValueType::List(item) => Some(*item),
+            ValueType::Grids => Some(ValueType::Grid),
+            ValueType::PairGrids => Some(ValueType::Tuple(vec![ValueType::Grid, ValueType::Grid])),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ValueType::List(item) => write!(f, "List({})", item),
+            ValueType::Tuple(items) => write!(f, "Tuple({})", items.iter().join(", ")),
+            ValueType::Option(item) => write!(f, "Option({})", item),
+            ValueType::Var(name) => write!(f, "{}", name),
+            other => write!(f, "{:?}", other),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -33,9 +162,14 @@ pub enum NeuronValue {
     Double(f64),
     Float(f32),
     Int8(i8),
-    Int16(i8),
+    Int16(i16),
     Int32(i32),
     Int64(i64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    Index(usize),
     String(String),
     Grid(Array2<i8>),
     Grids(Vec<Array2<i8> >),
@@ -44,11 +178,107 @@ pub enum NeuronValue {
     PairGrids(Vec<(Array2<i8>, Array2<i8>)>),
     LocationPairs(Vec<Vec<((isize, isize), (isize, isize))> >),
     RegionsList(Vec<Vec<Vec<(isize, isize)> > >),
+    /// Items and their type, which types an empty list
+    List(ValueType, Vec<NeuronValue>),
+    Tuple(Vec<NeuronValue>),
+    /// Value if any and its type
+    Option(ValueType, Option<Box<NeuronValue> >),
+}
+
+/// Rows of digits, cells being separated by spaces when a value isn't a single digit
+fn fmt_grid(f: &mut Formatter<'_>, grid: &Array2<i8>) -> Result {
+    let separator = if grid.iter().all(|v| (0..10).contains(v)) { "" } else { " " };
+
+    for (i, row) in grid.rows().into_iter().enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+
+        let cells: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+
+        write!(f, "{}", cells.join(separator))?;
+    }
+
+    Ok(())
+}
+
+fn fmt_list<T>(f: &mut Formatter<'_>, items: &[T], mut fmt_item: impl FnMut(&mut Formatter<'_>, &T) -> Result) -> Result {
+    write!(f, "[")?;
+
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+
+        fmt_item(f, item)?;
+    }
+
+    write!(f, "]")
 }
 
 impl Display for NeuronValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        write!(f, "{}", self)
+        match self {
+            NeuronValue::Bool(v) => write!(f, "{}", v),
+            NeuronValue::Char(v) => write!(f, "{}", v),
+            NeuronValue::Double(v) => write!(f, "{}", v),
+            NeuronValue::Float(v) => write!(f, "{}", v),
+            NeuronValue::Int8(v) => write!(f, "{}", v),
+            NeuronValue::Int16(v) => write!(f, "{}", v),
+            NeuronValue::Int32(v) => write!(f, "{}", v),
+            NeuronValue::Int64(v) => write!(f, "{}", v),
+            NeuronValue::UInt8(v) => write!(f, "{}", v),
+            NeuronValue::UInt16(v) => write!(f, "{}", v),
+            NeuronValue::UInt32(v) => write!(f, "{}", v),
+            NeuronValue::UInt64(v) => write!(f, "{}", v),
+            NeuronValue::Index(v) => write!(f, "{}", v),
+            NeuronValue::String(v) => write!(f, "{}", v),
+            NeuronValue::ValueType(t) => write!(f, "{}", t),
+            NeuronValue::Grid(g) => fmt_grid(f, g),
+            NeuronValue::Grids(grids) => {
+                for (i, g) in grids.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "\n\n")?;
+                    }
+
+                    fmt_grid(f, g)?;
+                }
+
+                Ok(())
+            }
+            NeuronValue::PairGrids(pairs) => {
+                for (i, (input, output)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "\n\n")?;
+                    }
+
+                    fmt_grid(f, input)?;
+                    write!(f, "\n→\n")?;
+                    fmt_grid(f, output)?;
+                }
+
+                Ok(())
+            }
+            NeuronValue::Map(m) => {
+                let mut entries: Vec<_> = m.iter().collect();
+
+                entries.sort();
+
+                let entries: Vec<String> = entries.iter().map(|(a, b)| format!("{}→{}", a, b)).collect();
+
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            NeuronValue::LocationPairs(pairs) => fmt_list(f, pairs, |f, grid_pairs| {
+                fmt_list(f, grid_pairs, |f, (a, b)| write!(f, "{:?}-{:?}", a, b))
+            }),
+            NeuronValue::RegionsList(regions) => fmt_list(f, regions, |f, grid_regions| {
+                fmt_list(f, grid_regions, |f, region| fmt_list(f, region, |f, at| write!(f, "{:?}", at)))
+            }),
+            NeuronValue::List(_, items) => fmt_list(f, items, |f, item| write!(f, "{}", item)),
+            NeuronValue::Tuple(items) => write!(f, "({})", items.iter().join(", ")),
+            NeuronValue::Option(_, Some(value)) => write!(f, "Some({})", value),
+            NeuronValue::Option(_, None) => write!(f, "None"),
+        }
     }
 }
 
@@ -61,6 +291,11 @@ impl PartialEq for NeuronValue {
             (NeuronValue::Int16(a), NeuronValue::Int16(b)) => a == b,
             (NeuronValue::Int32(a), NeuronValue::Int32(b)) => a == b,
             (NeuronValue::Int64(a), NeuronValue::Int64(b)) => a == b,
+            (NeuronValue::UInt8(a), NeuronValue::UInt8(b)) => a == b,
+            (NeuronValue::UInt16(a), NeuronValue::UInt16(b)) => a == b,
+            (NeuronValue::UInt32(a), NeuronValue::UInt32(b)) => a == b,
+            (NeuronValue::UInt64(a), NeuronValue::UInt64(b)) => a == b,
+            (NeuronValue::Index(a), NeuronValue::Index(b)) => a == b,
             (NeuronValue::Float(a), NeuronValue::Float(b)) => {
                 a.to_bits() == b.to_bits()
             }
@@ -75,6 +310,9 @@ impl PartialEq for NeuronValue {
             (NeuronValue::PairGrids(a), NeuronValue::PairGrids(b)) => a == b,
             (NeuronValue::LocationPairs(a), NeuronValue::LocationPairs(b)) => a == b,
             (NeuronValue::RegionsList(a), NeuronValue::RegionsList(b)) => a == b,
+            (NeuronValue::List(ta, a), NeuronValue::List(tb, b)) => ta == tb && a == b,
+            (NeuronValue::Tuple(a), NeuronValue::Tuple(b)) => a == b,
+            (NeuronValue::Option(ta, a), NeuronValue::Option(tb, b)) => ta == tb && a == b,
             _ => false,
         }
     }
@@ -84,6 +322,8 @@ impl Eq for NeuronValue {}
 
 impl Hash for NeuronValue {
     fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
         match self {
             NeuronValue::Bool(v) => v.hash(state),
             NeuronValue::Char(v) => v.hash(state),
@@ -91,16 +331,36 @@ impl Hash for NeuronValue {
             NeuronValue::Int16(v) => v.hash(state),
             NeuronValue::Int32(v) => v.hash(state),
             NeuronValue::Int64(v) => v.hash(state),
+            NeuronValue::UInt8(v) => v.hash(state),
+            NeuronValue::UInt16(v) => v.hash(state),
+            NeuronValue::UInt32(v) => v.hash(state),
+            NeuronValue::UInt64(v) => v.hash(state),
+            NeuronValue::Index(v) => v.hash(state),
             NeuronValue::Float(v) => v.to_bits().hash(state),
             NeuronValue::Double(v) => v.to_bits().hash(state),
             NeuronValue::Grid(v) => v.hash(state),
             NeuronValue::Grids(v) => v.hash(state),
             NeuronValue::ValueType(v) => v.hash(state),
             NeuronValue::String(v) => v.hash(state),
-            NeuronValue::Map(v) => v.iter().collect::<Vec<_> >().hash(state),
+            NeuronValue::Map(v) => {
+                // Equal maps iterate in different orders
+                let mut entries: Vec<_> = v.iter().collect();
+
+                entries.sort();
+                entries.hash(state)
+            }
             NeuronValue::PairGrids(v) => v.hash(state),
             NeuronValue::LocationPairs(v) => v.hash(state),
             NeuronValue::RegionsList(v) => v.hash(state),
+            NeuronValue::List(t, v) => {
+                t.hash(state);
+                v.hash(state)
+            }
+            NeuronValue::Tuple(v) => v.hash(state),
+            NeuronValue::Option(t, v) => {
+                t.hash(state);
+                v.hash(state)
+            }
         }
     }
 }
@@ -116,6 +376,11 @@ impl NeuronValue {
             NeuronValue::Int16(_) => ValueType::Int16,
             NeuronValue::Int32(_) => ValueType::Int32,
             NeuronValue::Int64(_) => ValueType::Int64,
+            NeuronValue::UInt8(_) => ValueType::UInt8,
+            NeuronValue::UInt16(_) => ValueType::UInt16,
+            NeuronValue::UInt32(_) => ValueType::UInt32,
+            NeuronValue::UInt64(_) => ValueType::UInt64,
+            NeuronValue::Index(_) => ValueType::Index,
             NeuronValue::String(_) => ValueType::String,
             NeuronValue::Grid(_) => ValueType::Grid,
             NeuronValue::Grids(_) => ValueType::Grids,
@@ -124,45 +389,171 @@ impl NeuronValue {
             NeuronValue::PairGrids(_) => ValueType::PairGrids,
             NeuronValue::LocationPairs(_) => ValueType::LocationPairs,
             NeuronValue::RegionsList(_) => ValueType::RegionsList,
+            NeuronValue::List(t, _) => ValueType::list(t.clone()),
+            NeuronValue::Tuple(items) => ValueType::Tuple(items.iter().map(NeuronValue::value_type).collect()),
+            NeuronValue::Option(t, _) => ValueType::Option(Box::new(t.clone())),
+        }
+    }
+
+    /// List of `items` of type `item_type`, lists of grids and of grid pairs being built as `Grids`
+    /// and `PairGrids`
+    pub fn list(item_type: ValueType, items: Vec<NeuronValue>) -> NeuronValue {
+        let grid = |value: NeuronValue| match value {
+            NeuronValue::Grid(g) => Some(g),
+            _ => None,
+        };
+
+        match ValueType::list(item_type.clone()) {
+            ValueType::Grids => NeuronValue::Grids(items.into_iter().filter_map(grid).collect()),
+            ValueType::PairGrids => NeuronValue::PairGrids(
+                items
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        NeuronValue::Tuple(pair) => pair.into_iter().filter_map(grid).collect_tuple(),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => NeuronValue::List(item_type.normalized(), items),
+        }
+    }
+
+    /// Items of a list value, including the grids of `Grids` and the pairs of `PairGrids`
+    pub fn items(&self) -> Option<Vec<NeuronValue> > {
+        match self {
+            NeuronValue::List(_, items) => Some(items.clone()),
+            NeuronValue::Grids(grids) => Some(grids.iter().cloned().map(NeuronValue::Grid).collect()),
+            NeuronValue::PairGrids(pairs) => Some(
+                pairs
+                    .iter()
+                    .map(|(a, b)| NeuronValue::Tuple(vec![NeuronValue::Grid(a.clone()), NeuronValue::Grid(b.clone())]))
+                    .collect(),
+            ),
+            _ => None,
         }
     }
 
+    /// Approximate number of bytes held by the value, its heap allocations included
+    pub fn size(&self) -> usize {
+        let grid = |g: &Array2<i8>| std::mem::size_of::<Array2<i8> >() + g.len();
+        let heap = match self {
+            NeuronValue::Char(s) | NeuronValue::String(s) => s.len(),
+            NeuronValue::Grid(g) => g.len(),
+            NeuronValue::Grids(grids) => grids.iter().map(grid).sum(),
+            NeuronValue::Map(map) => map.len() * 2 * std::mem::size_of::<i8>(),
+            NeuronValue::PairGrids(pairs) => pairs.iter().map(|(a, b)| grid(a) + grid(b)).sum(),
+            NeuronValue::LocationPairs(pairs) => pairs
+                .iter()
+                .map(|l| std::mem::size_of_val(l) + std::mem::size_of_val(l.as_slice()))
+                .sum(),
+            NeuronValue::RegionsList(regions) => regions
+                .iter()
+                .flatten()
+                .map(|r| std::mem::size_of_val(r) + std::mem::size_of_val(r.as_slice()))
+                .sum(),
+            NeuronValue::List(_, items) | NeuronValue::Tuple(items) => {
+                items.iter().map(NeuronValue::size).sum()
+            }
+            NeuronValue::Option(_, value) => value.as_ref().map_or(0, |v| v.size()),
+            _ => 0,
+        };
+
+        std::mem::size_of::<NeuronValue>() + heap
+    }
+
+    /// Distance to `target` with grids compared by `ColorDistance`
     pub fn heuristic(&self, target: &NeuronValue) -> f64 {
+        self.distance(target, &ColorDistance)
+    }
+
+    /// Distance to `target`, 0 meaning equal and infinity meaning incomparable, with grids compared by `metric`
+    pub fn distance(&self, target: &NeuronValue, metric: &dyn GridMetric) -> f64 {
+        let grid_distance = |a: &Array2<i8>, b: &Array2<i8>| metric.distance(a, b);
+
         match (self, target) {
             (NeuronValue::Bool(a), NeuronValue::Bool(b)) => (*a as i32 - *b as i32).abs() as f64,
             (NeuronValue::Char(a), NeuronValue::Char(b)) => levenshtein(a, b) as f64,
-            (NeuronValue::Int64(a), NeuronValue::Int64(b)) => (*a - *b).abs() as f64,
-            (NeuronValue::Int32(a), NeuronValue::Int32(b)) => (*a - *b).abs() as f64,
+            (NeuronValue::Int8(a), NeuronValue::Int8(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::Int64(a), NeuronValue::Int64(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::Int32(a), NeuronValue::Int32(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::Int16(a), NeuronValue::Int16(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::UInt8(a), NeuronValue::UInt8(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::UInt16(a), NeuronValue::UInt16(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::UInt32(a), NeuronValue::UInt32(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::UInt64(a), NeuronValue::UInt64(b)) => a.abs_diff(*b) as f64,
+            (NeuronValue::Index(a), NeuronValue::Index(b)) => a.abs_diff(*b) as f64,
             (NeuronValue::Float(a), NeuronValue::Float(b)) => (*a - *b).abs() as f64,
             (NeuronValue::Double(a), NeuronValue::Double(b)) => (*a - *b).abs(),
             (NeuronValue::String(a), NeuronValue::String(b)) => levenshtein(a, b) as f64,
-            (NeuronValue::Grid(val), NeuronValue::Grid(target)) => {
-                if val.shape() != target.shape() {
-                    return 100.0 + (val.sum() - target.sum()).abs() as f64;
-                }
-
-                val.iter()
-                    .zip(target.iter())
-                    .map(|(a, b)| (a - b).abs())
-                    .sum::<i8>() as f64
+            (NeuronValue::ValueType(a), NeuronValue::ValueType(b)) => (a != b) as i32 as f64,
+            (NeuronValue::Grid(val), NeuronValue::Grid(target)) => grid_distance(val, target),
+            (NeuronValue::Grids(val), NeuronValue::Grids(target)) => list_distance(val, target, grid_distance),
+            (NeuronValue::Map(val), NeuronValue::Map(target)) => symmetric_difference(val.iter(), target.iter()),
+            (NeuronValue::PairGrids(val), NeuronValue::PairGrids(target)) => {
+                list_distance(val, target, |(a0, a1), (b0, b1)| grid_distance(a0, b0) + grid_distance(a1, b1))
             }
-            (NeuronValue::Grids(val), NeuronValue::Grids(target)) => {
-                if val.len() != target.len() {
-                    return 100.0 + (val.len() as f64 - target.len() as f64).abs();
-                }
-
-                let x: Vec<f64> = val.iter()
-                    .zip(target.iter())
-                    .map(|(a, b)| NeuronValue::Grid(a.clone()).heuristic(&NeuronValue::Grid(b.clone())))
-                    .collect();
+            (NeuronValue::LocationPairs(val), NeuronValue::LocationPairs(target)) => {
+                list_distance(val, target, |a, b| location_pairs_distance(a, b))
+            }
+            (NeuronValue::RegionsList(val), NeuronValue::RegionsList(target)) => {
+                list_distance(val, target, |a, b| {
+                    let sorted = |regions: &Vec<Vec<(isize, isize)> >| -> Vec<Vec<(isize, isize)> > {
+                        regions.iter().map(|r| r.iter().copied().sorted().collect()).collect()
+                    };
 
-                x.iter().map(|d| d * d).sum::<f64>().sqrt()
+                    symmetric_difference(sorted(a).into_iter(), sorted(b).into_iter())
+                })
             }
+            (NeuronValue::List(_, val), NeuronValue::List(_, target)) => {
+                list_distance(val, target, |a, b| a.distance(b, metric))
+            }
+            (NeuronValue::Tuple(val), NeuronValue::Tuple(target)) if val.len() == target.len() => {
+                val.iter().zip(target).map(|(a, b)| a.distance(b, metric)).sum()
+            }
+            (NeuronValue::Option(_, val), NeuronValue::Option(_, target)) => match (val, target) {
+                (Some(a), Some(b)) => a.distance(b, metric),
+                (None, None) => 0.0,
+                _ => MISSING_COST,
+            },
             _ => f64::INFINITY,
         }
     }
 }
 
+/// Euclidean norm of the element-wise distances, lists of different lengths being `MISSING_COST` apart at least
+fn list_distance<T>(val: &[T], target: &[T], distance: impl Fn(&T, &T) -> f64) -> f64 {
+    if val.len() != target.len() {
+        return MISSING_COST + (val.len() as f64 - target.len() as f64).abs();
+    }
+
+    val.iter()
+        .zip(target)
+        .map(|(a, b)| distance(a, b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn symmetric_difference<T: Eq + Hash>(val: impl Iterator<Item = T>, target: impl Iterator<Item = T>) -> f64 {
+    let val: HashSet<T> = val.collect();
+    let target: HashSet<T> = target.collect();
+
+    val.symmetric_difference(&target).count() as f64
+}
+
+type LocationPair = ((isize, isize), (isize, isize));
+
+/// Cost of the best one-to-one matching of the location pairs, a pair costing the Manhattan distances
+/// between its locations and the matched ones, and `MISSING_COST` if unmatched
+fn location_pairs_distance(val: &[LocationPair], target: &[LocationPair]) -> f64 {
+    if val == target {
+        return 0.0;
+    }
+
+    let manhattan = |a: (isize, isize), b: (isize, isize)| (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as f64;
+
+    matching_distance(val, target, |a, b| manhattan(a.0, b.0) + manhattan(a.1, b.1), |_| MISSING_COST)
+}
+
 pub type NeuronFn = dyn Fn(&[NeuronValue]) -> Option<NeuronValue> + Send + Sync;
 
 pub struct Neuron {
@@ -192,11 +583,16 @@ impl Neuron {
         Self {
             name: name.into(),
             function,
-            input_types,
-            output_type,
+            input_types: input_types.iter().map(ValueType::normalized).collect(),
+            output_type: output_type.normalized(),
         }
     }
 
+    /// Constant neuron without value, such as the `input` of a per-example search, to be bound by a `Context`
+    pub fn variable(name: impl Into<String>, value_type: ValueType) -> Self {
+        Self::new(name, RwLock::new(Arc::new(|_: &[NeuronValue]| None)), vec![], value_type)
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
@@ -209,6 +605,21 @@ impl Neuron {
         &self.output_type
     }
 
+    /// Whether the signature has type variables, the neuron being instantiated before a search
+    pub fn is_polymorphic(&self) -> bool {
+        self.input_types.iter().any(ValueType::is_polymorphic) || self.output_type.is_polymorphic()
+    }
+
+    /// Neuron of the same name and function with the type variables of its signature replaced
+    pub fn instantiate(&self, bindings: &TypeBindings) -> Neuron {
+        Neuron::new(
+            self.name.clone(),
+            RwLock::new(Arc::clone(&self.function.read().expect("Lock poisoned"))),
+            self.input_types.iter().map(|t| t.substitute(bindings)).collect(),
+            self.output_type.substitute(bindings),
+        )
+    }
+
     pub fn apply(&self, args: &[NeuronValue]) -> Option<NeuronValue> {
         let func = self.function.read().unwrap();
 
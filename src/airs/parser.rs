@@ -0,0 +1,513 @@
+use ndarray::Array2;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::connection::{Connection, ConnectionValue};
+use super::neuron::{Neuron, NeuronValue, TypeBindings, ValueType};
+use super::registry::NeuronRegistry;
+
+/// Reason why a program could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The program ended before a name, a `,` or a `)`
+    UnexpectedEnd,
+    /// Character found at a byte offset where it is not allowed
+    UnexpectedChar(char, usize),
+    /// The program doesn't start with the name of a neuron
+    UnknownNeuron(String),
+    /// The neuron is given a wrong number of inputs
+    Arity { neuron: String, expected: usize, found: usize },
+    /// The neuron or hole doesn't have the input type expected by its parent
+    TypeMismatch { expected: ValueType, found: ValueType },
+    /// The literal can't be read as a value of the input type
+    InvalidLiteral { literal: String, value_type: ValueType },
+    /// The type argument of `List`, `Tuple` or `Option` isn't a type
+    UnknownType(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd => write!(f, "Unexpected end of program"),
+            ParseError::UnexpectedChar(c, i) => write!(f, "Unexpected '{}' at {}", c, i),
+            ParseError::UnknownNeuron(name) => write!(f, "Unknown neuron {}", name),
+            ParseError::Arity { neuron, expected, found } => {
+                write!(f, "Neuron {} takes {} inputs, {} given", neuron, expected, found)
+            }
+            ParseError::TypeMismatch { expected, found } => write!(f, "Expected {:?}, found {:?}", expected, found),
+            ParseError::InvalidLiteral { literal, value_type } => write!(f, "Invalid {:?} literal {}", value_type, literal),
+            ParseError::UnknownType(name) => write!(f, "Unknown type {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Value type without arguments printed by `Connection::to_string` for a hole
+fn value_type(name: &str) -> Option<ValueType> {
+    let value_type = match name {
+        "Bool" => ValueType::Bool,
+        "Char" => ValueType::Char,
+        "Double" => ValueType::Double,
+        "Float" => ValueType::Float,
+        "Int8" => ValueType::Int8,
+        "Int16" => ValueType::Int16,
+        "Int32" => ValueType::Int32,
+        "Int64" => ValueType::Int64,
+        "UInt8" => ValueType::UInt8,
+        "UInt16" => ValueType::UInt16,
+        "UInt32" => ValueType::UInt32,
+        "UInt64" => ValueType::UInt64,
+        "Index" => ValueType::Index,
+        "String" => ValueType::String,
+        "Grid" => ValueType::Grid,
+        "Grids" => ValueType::Grids,
+        "Type" => ValueType::Type,
+        "Map" => ValueType::Map,
+        "PairGrids" => ValueType::PairGrids,
+        "LocationPairs" => ValueType::LocationPairs,
+        "RegionsList" => ValueType::RegionsList,
+        _ => return None,
+    };
+
+    Some(value_type)
+}
+
+/// Value of a literal without delimiters, such as a number or a boolean
+fn scalar(token: &str, value_type: &ValueType) -> Option<NeuronValue> {
+    let value = match value_type {
+        ValueType::Bool => NeuronValue::Bool(token.parse().ok()?),
+        ValueType::Double => NeuronValue::Double(token.parse().ok()?),
+        ValueType::Float => NeuronValue::Float(token.parse().ok()?),
+        ValueType::Int8 => NeuronValue::Int8(token.parse().ok()?),
+        ValueType::Int16 => NeuronValue::Int16(token.parse().ok()?),
+        ValueType::Int32 => NeuronValue::Int32(token.parse().ok()?),
+        ValueType::Int64 => NeuronValue::Int64(token.parse().ok()?),
+        ValueType::UInt8 => NeuronValue::UInt8(token.parse().ok()?),
+        ValueType::UInt16 => NeuronValue::UInt16(token.parse().ok()?),
+        ValueType::UInt32 => NeuronValue::UInt32(token.parse().ok()?),
+        ValueType::UInt64 => NeuronValue::UInt64(token.parse().ok()?),
+        ValueType::Index => NeuronValue::Index(token.parse().ok()?),
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// `text` between `quote`s, a `\` escaping the next character
+fn quoted(text: &str, quote: char) -> String {
+    let mut quoted = String::from(quote);
+
+    for c in text.chars() {
+        if c == '\\' || c == quote {
+            quoted.push('\\');
+        }
+
+        quoted.push(c);
+    }
+
+    quoted.push(quote);
+    quoted
+}
+
+fn bracketed<T>(items: &[T], item: impl Fn(&T) -> String) -> String {
+    format!("[{}]", items.iter().map(item).collect::<Vec<_> >().join(", "))
+}
+
+fn grid_literal(grid: &Array2<i8>) -> String {
+    let rows: Vec<Vec<i8> > = grid.rows().into_iter().map(|row| row.to_vec()).collect();
+
+    bracketed(&rows, |row| bracketed(row, i8::to_string))
+}
+
+fn location_literal((i, j): &(isize, isize)) -> String {
+    format!("({}, {})", i, j)
+}
+
+/// Text of a value given to a neuron in `Connection::to_string`, read back by `parse_connection` at the
+/// type of the input: strings and chars are quoted, grids and lists bracketed, maps braced and tuples
+/// parenthesized
+pub(super) fn literal_string(value: &NeuronValue) -> String {
+    match value {
+        NeuronValue::String(s) => quoted(s, '"'),
+        NeuronValue::Char(c) => quoted(c, '\''),
+        NeuronValue::Grid(g) => grid_literal(g),
+        NeuronValue::Grids(grids) => bracketed(grids, grid_literal),
+        NeuronValue::PairGrids(pairs) => {
+            bracketed(pairs, |(a, b)| format!("({}, {})", grid_literal(a), grid_literal(b)))
+        }
+        NeuronValue::LocationPairs(pairs) => bracketed(pairs, |grid_pairs| {
+            bracketed(grid_pairs, |(a, b)| format!("({}, {})", location_literal(a), location_literal(b)))
+        }),
+        NeuronValue::RegionsList(regions) => bracketed(regions, |grid_regions| {
+            bracketed(grid_regions, |region| bracketed(region, location_literal))
+        }),
+        NeuronValue::List(_, items) => bracketed(items, literal_string),
+        NeuronValue::Tuple(items) => format!("({})", items.iter().map(literal_string).collect::<Vec<_> >().join(", ")),
+        NeuronValue::Option(_, Some(value)) => format!("Some({})", literal_string(value)),
+        // Numbers, booleans and maps read back as they are displayed
+        _ => value.to_string(),
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    registry: &'a NeuronRegistry,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.text[self.pos..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(found) if found == c => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            Some(found) => Err(ParseError::UnexpectedChar(found, self.pos)),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    /// Name of a neuron, hole or scalar literal, up to the next delimiter
+    fn token(&mut self) -> Result<&'a str, ParseError> {
+        self.skip_whitespace();
+
+        let rest = &self.text[self.pos..];
+        let end = rest.find(['(', ',', ')', '[', ']', '{', '}', '→']).unwrap_or(rest.len());
+        let token = rest[..end].trim_end();
+
+        if token.is_empty() {
+            return match rest.chars().next() {
+                Some(c) => Err(ParseError::UnexpectedChar(c, self.pos)),
+                None => Err(ParseError::UnexpectedEnd),
+            };
+        }
+
+        self.pos += end;
+
+        Ok(token)
+    }
+
+    /// Type printed by `ValueType`'s `Display`, the arguments of `List`, `Tuple` and `Option` following the name
+    fn value_type(&mut self, name: &str) -> Result<Option<ValueType>, ParseError> {
+        if !matches!(name, "List" | "Tuple" | "Option") || self.peek() != Some('(') {
+            return Ok(value_type(name));
+        }
+
+        self.expect('(')?;
+
+        let mut items = vec![self.type_argument()?];
+
+        while name == "Tuple" && self.peek() == Some(',') {
+            self.expect(',')?;
+            items.push(self.type_argument()?);
+        }
+
+        self.expect(')')?;
+
+        let value_type = match name {
+            "List" => ValueType::List(Box::new(items.remove(0))),
+            "Option" => ValueType::Option(Box::new(items.remove(0))),
+            _ => ValueType::Tuple(items),
+        };
+
+        Ok(Some(value_type.normalized()))
+    }
+
+    fn type_argument(&mut self) -> Result<ValueType, ParseError> {
+        let name = self.token()?;
+
+        self.value_type(name)?.ok_or_else(|| ParseError::UnknownType(name.to_string()))
+    }
+
+    fn neuron(&self, name: &str) -> Option<&'a Arc<Neuron> > {
+        self.registry.get(name)
+    }
+
+    /// Connection of `neuron` to the inputs that follow, a polymorphic neuron being instantiated
+    /// at the types of its inputs
+    fn connection(&mut self, name: &str, neuron: &Arc<Neuron>) -> Result<Connection, ParseError> {
+        let input_types = neuron.input_types();
+        let mut inputs = Vec::with_capacity(input_types.len());
+        let mut bindings = TypeBindings::new();
+
+        if self.peek() == Some('(') {
+            self.expect('(')?;
+
+            loop {
+                let Some(input_type) = input_types.get(inputs.len()) else {
+                    return Err(ParseError::Arity {
+                        neuron: name.to_string(),
+                        expected: input_types.len(),
+                        found: inputs.len() + 1,
+                    });
+                };
+
+                inputs.push(self.value(input_type, &mut bindings)?);
+
+                match self.peek() {
+                    Some(',') => self.expect(',')?,
+                    _ => break,
+                }
+            }
+
+            self.expect(')')?;
+        }
+
+        if inputs.len() != input_types.len() {
+            return Err(ParseError::Arity {
+                neuron: name.to_string(),
+                expected: input_types.len(),
+                found: inputs.len(),
+            });
+        }
+
+        let neuron = match neuron.is_polymorphic() {
+            true => Arc::new(neuron.instantiate(&bindings)),
+            false => Arc::clone(neuron),
+        };
+
+        Ok(Connection::new(neuron, &inputs))
+    }
+
+    /// Input of a neuron: a neuron name takes precedence over a hole or a literal of the same text;
+    /// its type is unified with `expected`, binding the type variables of the parent neuron
+    fn value(&mut self, expected: &ValueType, bindings: &mut TypeBindings) -> Result<ConnectionValue, ParseError> {
+        let expected = expected.substitute(bindings);
+
+        if matches!(self.peek(), Some('[' | '{' | '(' | '"' | '\'')) {
+            return self.literal(&expected).map(ConnectionValue::Value);
+        }
+
+        let token = self.token()?;
+        let mismatch = |found: &ValueType| ParseError::TypeMismatch {
+            expected: expected.clone(),
+            found: found.clone(),
+        };
+
+        if let Some(neuron) = self.neuron(token) {
+            let connection = self.connection(token, neuron)?;
+            let found = &connection.neuron().output_type().clone();
+
+            if !expected.unify(found, bindings) {
+                return Err(mismatch(found));
+            }
+
+            return Ok(ConnectionValue::Connection(Arc::new(connection)));
+        }
+
+        if let Some(hole) = self.value_type(token)? {
+            if !expected.unify(&hole, bindings) {
+                return Err(mismatch(&hole));
+            }
+
+            return Ok(ConnectionValue::Value(NeuronValue::ValueType(hole)));
+        }
+
+        self.token_literal(token, &expected).map(ConnectionValue::Value)
+    }
+
+    /// Literal of `value_type` printed by `literal_string`
+    fn literal(&mut self, value_type: &ValueType) -> Result<NeuronValue, ParseError> {
+        let value = match value_type {
+            ValueType::String => NeuronValue::String(self.quoted('"')?),
+            ValueType::Char => NeuronValue::Char(self.quoted('\'')?),
+            ValueType::Grid => NeuronValue::Grid(self.grid()?),
+            ValueType::Grids => NeuronValue::Grids(self.list(Self::grid)?),
+            ValueType::PairGrids => NeuronValue::PairGrids(self.list(|p| p.pair(Self::grid))?),
+            ValueType::Map => NeuronValue::Map(self.map()?),
+            ValueType::LocationPairs => {
+                NeuronValue::LocationPairs(self.list(|p| p.list(|p| p.pair(Self::location)))?)
+            }
+            ValueType::RegionsList => NeuronValue::RegionsList(self.list(|p| p.list(|p| p.list(Self::location)))?),
+            ValueType::List(item_type) => NeuronValue::list(*item_type.clone(), self.list(|p| p.literal(item_type))?),
+            ValueType::Tuple(item_types) => {
+                self.expect('(')?;
+
+                let mut items = Vec::with_capacity(item_types.len());
+
+                for (i, item_type) in item_types.iter().enumerate() {
+                    if i > 0 {
+                        self.expect(',')?;
+                    }
+
+                    items.push(self.literal(item_type)?);
+                }
+
+                self.expect(')')?;
+                NeuronValue::Tuple(items)
+            }
+            _ => {
+                let token = self.token()?;
+
+                self.token_literal(token, value_type)?
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Literal of `value_type` starting with `token`, an option or a scalar
+    fn token_literal(&mut self, token: &str, value_type: &ValueType) -> Result<NeuronValue, ParseError> {
+        let invalid = || ParseError::InvalidLiteral {
+            literal: token.to_string(),
+            value_type: value_type.clone(),
+        };
+
+        match (value_type, token) {
+            (ValueType::Option(item_type), "None") => Ok(NeuronValue::Option(*item_type.clone(), None)),
+            (ValueType::Option(item_type), "Some") => {
+                self.expect('(')?;
+
+                let value = self.literal(item_type)?;
+
+                self.expect(')')?;
+                Ok(NeuronValue::Option(*item_type.clone(), Some(Box::new(value))))
+            }
+            (ValueType::String | ValueType::Char, _) => Err(invalid()),
+            _ if token.is_empty() => Err(invalid()),
+            _ => scalar(token, value_type).ok_or_else(invalid),
+        }
+    }
+
+    /// Text between `quote`s, unescaped
+    fn quoted(&mut self, quote: char) -> Result<String, ParseError> {
+        self.expect(quote)?;
+
+        let rest: &'a str = &self.text[self.pos..];
+        let mut text = String::new();
+        let mut chars = rest.chars();
+
+        while let Some(c) = chars.next() {
+            self.pos += c.len_utf8();
+
+            match c {
+                '\\' => {
+                    let escaped = chars.next().ok_or(ParseError::UnexpectedEnd)?;
+
+                    self.pos += escaped.len_utf8();
+                    text.push(escaped);
+                }
+                c if c == quote => return Ok(text),
+                c => text.push(c),
+            }
+        }
+
+        Err(ParseError::UnexpectedEnd)
+    }
+
+    /// Items between brackets, separated by commas
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        self.expect('[')?;
+
+        let mut items = Vec::new();
+
+        if self.peek() != Some(']') {
+            loop {
+                items.push(item(self)?);
+
+                match self.peek() {
+                    Some(',') => self.expect(',')?,
+                    _ => break,
+                }
+            }
+        }
+
+        self.expect(']')?;
+        Ok(items)
+    }
+
+    fn pair<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, ParseError>) -> Result<(T, T), ParseError> {
+        self.expect('(')?;
+
+        let a = item(self)?;
+
+        self.expect(',')?;
+
+        let b = item(self)?;
+
+        self.expect(')')?;
+        Ok((a, b))
+    }
+
+    fn number<T: FromStr>(&mut self, value_type: ValueType) -> Result<T, ParseError> {
+        let token = self.token()?;
+
+        token.parse().map_err(|_| ParseError::InvalidLiteral {
+            literal: token.to_string(),
+            value_type,
+        })
+    }
+
+    fn location(&mut self) -> Result<(isize, isize), ParseError> {
+        self.pair(|p| p.number(ValueType::Int64))
+    }
+
+    /// Rows of cells, every row having the same length
+    fn grid(&mut self) -> Result<Array2<i8>, ParseError> {
+        let start = self.pos;
+        let rows = self.list(|p| p.list(|p| p.number::<i8>(ValueType::Int8)))?;
+        let columns = rows.first().map_or(0, Vec::len);
+
+        Array2::from_shape_vec((rows.len(), columns), rows.concat()).map_err(|_| ParseError::InvalidLiteral {
+            literal: self.text[start..self.pos].trim().to_string(),
+            value_type: ValueType::Grid,
+        })
+    }
+
+    /// Entries `key→value` between braces, as printed by `NeuronValue`'s `Display`
+    fn map(&mut self) -> Result<HashMap<i8, i8>, ParseError> {
+        self.expect('{')?;
+
+        let mut map = HashMap::new();
+
+        if self.peek() != Some('}') {
+            loop {
+                let key = self.number(ValueType::Int8)?;
+
+                self.expect('→')?;
+                map.insert(key, self.number(ValueType::Int8)?);
+
+                match self.peek() {
+                    Some(',') => self.expect(',')?,
+                    _ => break,
+                }
+            }
+        }
+
+        self.expect('}')?;
+        Ok(map)
+    }
+}
+
+/// Parse a program printed by `Connection::to_string`, resolving names against `registry`;
+/// names that aren't neurons are read as holes or literals of the input type they're given to
+pub fn parse_connection(text: &str, registry: &NeuronRegistry) -> Result<Arc<Connection>, ParseError> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        registry,
+    };
+
+    let name = parser.token()?;
+    let neuron = parser.neuron(name).ok_or_else(|| ParseError::UnknownNeuron(name.to_string()))?;
+    let connection = parser.connection(name, neuron)?;
+
+    match parser.peek() {
+        Some(c) => Err(ParseError::UnexpectedChar(c, parser.pos)),
+        None => Ok(Arc::new(connection)),
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::brain::SearchConfig;
+use super::cache::{CacheStats, OutputCache};
+use super::connection::Connection;
+
+/// Number of evaluations between two calls of `SearchObserver::on_progress`
+pub const PROGRESS_INTERVAL: usize = 4096;
+
+/// Counts of a search, the best cost and program being over all its targets
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchStats {
+    /// Connections generated at each level, the neurons without inputs being at level 0
+    pub connections_per_level: Vec<usize>,
+    pub evaluations: usize,
+    pub elapsed: Duration,
+    pub best_cost: f64,
+    pub best_program: Option<String>,
+    /// Statistics of the output cache of the search if any
+    pub cache: Option<CacheStats>,
+}
+
+impl Default for SearchStats {
+    fn default() -> Self {
+        Self {
+            connections_per_level: Vec::new(),
+            evaluations: 0,
+            elapsed: Duration::ZERO,
+            best_cost: f64::INFINITY,
+            best_program: None,
+            cache: None,
+        }
+    }
+}
+
+impl SearchStats {
+    pub fn evaluations_per_second(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            0.0 => 0.0,
+            seconds => self.evaluations as f64 / seconds,
+        }
+    }
+}
+
+/// Receives the statistics of a running search, possibly from several threads; every method does
+/// nothing by default
+pub trait SearchObserver: Debug + Send + Sync {
+    /// The connections of `level` were generated; not called by `BestFirst`, which mixes the levels
+    fn on_level(&self, _level: usize, _stats: &SearchStats) {}
+
+    /// A candidate better than the previous ones was found
+    fn on_best(&self, _stats: &SearchStats) {}
+
+    /// `PROGRESS_INTERVAL` more candidates were evaluated
+    fn on_progress(&self, _stats: &SearchStats) {}
+
+    /// The search ended, with its final statistics
+    fn on_finish(&self, _stats: &SearchStats) {}
+}
+
+/// Statistics of a running search and the observer they are reported to
+pub(super) struct Progress {
+    observer: Option<Arc<dyn SearchObserver> >,
+    cache: Option<Arc<OutputCache> >,
+    start: Instant,
+    evaluations: AtomicUsize,
+    stats: Mutex<SearchStats>,
+}
+
+impl Progress {
+    pub(super) fn new(config: &SearchConfig) -> Self {
+        Self {
+            observer: config.observer.clone(),
+            cache: config.cache.clone(),
+            start: Instant::now(),
+            evaluations: AtomicUsize::new(0),
+            stats: Mutex::new(SearchStats::default()),
+        }
+    }
+
+    /// Update the statistics, then pass them to the observer with `notify` if `update` returns true
+    fn update(
+        &self,
+        update: impl FnOnce(&mut SearchStats) -> bool,
+        notify: impl FnOnce(&dyn SearchObserver, &SearchStats),
+    ) {
+        let mut stats = self.stats.lock().expect("Lock poisoned");
+
+        if !update(&mut stats) {
+            return;
+        }
+
+        stats.evaluations = self.evaluations.load(Ordering::Relaxed);
+        stats.elapsed = self.start.elapsed();
+        stats.cache = self.cache.as_ref().map(|c| c.stats());
+
+        if let Some(observer) = &self.observer {
+            notify(observer.as_ref(), &stats);
+        }
+    }
+
+    /// Count `connections` more connections generated at `level`
+    pub(super) fn generated(&self, level: usize, connections: usize) {
+        let mut stats = self.stats.lock().expect("Lock poisoned");
+
+        if stats.connections_per_level.len() <= level {
+            stats.connections_per_level.resize(level + 1, 0);
+        }
+
+        stats.connections_per_level[level] += connections;
+    }
+
+    /// The connections of `level` were generated, `evaluations` candidates being evaluated so far
+    pub(super) fn level(&self, level: usize, evaluations: usize) {
+        self.evaluations.fetch_max(evaluations, Ordering::Relaxed);
+        self.update(
+            |stats| {
+                if stats.connections_per_level.len() <= level {
+                    stats.connections_per_level.resize(level + 1, 0);
+                }
+
+                true
+            },
+            |observer, stats| observer.on_level(level, stats),
+        );
+    }
+
+    /// `evaluations` candidates were evaluated so far, reported every `PROGRESS_INTERVAL` evaluations
+    pub(super) fn evaluated(&self, evaluations: usize) {
+        self.evaluations.fetch_max(evaluations, Ordering::Relaxed);
+
+        if self.observer.is_some() && evaluations.is_multiple_of(PROGRESS_INTERVAL) {
+            self.update(|_| true, |observer, stats| observer.on_progress(stats));
+        }
+    }
+
+    /// A candidate of `cost` entered the ranking of a target, tracked only for the observer since `finish`
+    /// takes the best candidate from the final ranking
+    pub(super) fn candidate(&self, cost: f64, connection: &Connection) {
+        if self.observer.is_none() {
+            return;
+        }
+
+        self.update(
+            |stats| {
+                if cost >= stats.best_cost {
+                    return false;
+                }
+
+                stats.best_cost = cost;
+                stats.best_program = Some(connection.to_string());
+                true
+            },
+            |observer, stats| observer.on_best(stats),
+        );
+    }
+
+    /// Final statistics of a search of `evaluations` candidates, `best` being its best candidate and cost
+    pub(super) fn finish(&self, evaluations: usize, best: Option<(f64, &Connection)>) -> SearchStats {
+        let mut stats = self.stats.lock().expect("Lock poisoned");
+
+        if let Some((cost, connection)) = best {
+            stats.best_cost = cost;
+            stats.best_program = Some(connection.to_string());
+        }
+
+        stats.evaluations = evaluations;
+        stats.elapsed = self.start.elapsed();
+        stats.cache = self.cache.as_ref().map(|c| c.stats());
+
+        if let Some(observer) = &self.observer {
+            observer.on_finish(&stats);
+        }
+
+        stats.clone()
+    }
+}
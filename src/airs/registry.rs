@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use super::neuron::{Neuron, ValueType};
+
+/// Reason why a neuron could not be registered
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    /// A neuron of the same name is already registered
+    Duplicate(String),
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::Duplicate(name) => write!(f, "Neuron {} is already registered", name),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Neurons by unique name, indexed by output type and by input types, in registration order
+#[derive(Clone, Debug, Default)]
+pub struct NeuronRegistry {
+    neurons: Vec<Arc<Neuron> >,
+    names: HashMap<String, usize>,
+    outputs: HashMap<ValueType, Vec<usize> >,
+    signatures: HashMap<Vec<ValueType>, Vec<usize> >,
+}
+
+impl NeuronRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, neuron: Arc<Neuron>) -> Result<(), RegistryError> {
+        let name = neuron.name();
+
+        if self.names.contains_key(&name) {
+            return Err(RegistryError::Duplicate(name));
+        }
+
+        let index = self.neurons.len();
+
+        self.names.insert(name, index);
+        self.outputs.entry(neuron.output_type().clone()).or_default().push(index);
+        self.signatures.entry(neuron.input_types().to_vec()).or_default().push(index);
+        self.neurons.push(neuron);
+
+        Ok(())
+    }
+
+    /// Register every neuron, stopping at the first duplicate
+    pub fn register_all(&mut self, neurons: impl IntoIterator<Item = Arc<Neuron> >) -> Result<(), RegistryError> {
+        neurons.into_iter().try_for_each(|neuron| self.register(neuron))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<Neuron> > {
+        self.names.get(name).map(|&i| &self.neurons[i])
+    }
+
+    /// Neurons outputting `output_type`
+    #[cfg(test)]
+    pub fn by_output_type(&self, output_type: &ValueType) -> Vec<&Arc<Neuron> > {
+        self.lookup(self.outputs.get(output_type))
+    }
+
+    /// Neurons taking exactly `input_types` as inputs, the constants for an empty signature
+    #[cfg(test)]
+    pub fn by_input_types(&self, input_types: &[ValueType]) -> Vec<&Arc<Neuron> > {
+        self.lookup(self.signatures.get(input_types))
+    }
+
+    pub fn neurons(&self) -> &[Arc<Neuron> ] {
+        &self.neurons
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.neurons.len()
+    }
+
+    #[cfg(test)]
+    fn lookup(&self, indices: Option<&Vec<usize> >) -> Vec<&Arc<Neuron> > {
+        indices
+            .map(|indices| indices.iter().map(|&i| &self.neurons[i]).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl TryFrom<Vec<Arc<Neuron> > > for NeuronRegistry {
+    type Error = RegistryError;
+
+    fn try_from(neurons: Vec<Arc<Neuron> >) -> Result<Self, Self::Error> {
+        let mut registry = Self::new();
+
+        registry.register_all(neurons)?;
+
+        Ok(registry)
+    }
+}
@@ -1,6 +1,10 @@
 use ndarray::{Array2, Axis};
 use std::collections::{HashMap, HashSet};
 use std::cmp::min;
+use std::hash::Hash;
+
+/// Largest number of items matched optimally by `matching_distance` once the equal items are paired
+pub const MAX_MATCHING: usize = 64;
 
 #[inline]
 pub fn levenshtein(a: &str, b: &str) -> usize {
@@ -44,6 +48,78 @@ pub fn flipud(x: &Array2<i8>) -> Array2<i8> {
     y
 }
 
+/// Rotate a quarter turn counterclockwise
+pub fn rot90(x: &Array2<i8>) -> Array2<i8> {
+    let mut y = x.t().to_owned();
+    y.invert_axis(Axis(0));
+    y
+}
+
+pub fn transpose(x: &Array2<i8>) -> Array2<i8> {
+    x.t().to_owned()
+}
+
+/// Smallest sub-grid holding every cell different from `background`, the grid itself if there is none
+pub fn crop(x: &Array2<i8>, background: i8) -> Array2<i8> {
+    let cells: Vec<(usize, usize)> = x
+        .indexed_iter()
+        .filter(|(_, &v)| v != background)
+        .map(|(at, _)| at)
+        .collect();
+
+    let (Some(top), Some(bottom)) = (cells.iter().map(|c| c.0).min(), cells.iter().map(|c| c.0).max()) else {
+        return x.clone();
+    };
+    let left = cells.iter().map(|c| c.1).min().unwrap_or(0);
+    let right = cells.iter().map(|c| c.1).max().unwrap_or(0);
+
+    x.slice(ndarray::s![top..=bottom, left..=right]).to_owned()
+}
+
+/// Repeat a grid `rows` times vertically and `cols` times horizontally
+pub fn tile(x: &Array2<i8>, rows: usize, cols: usize) -> Array2<i8> {
+    let (h, w) = x.dim();
+
+    Array2::from_shape_fn((h * rows, w * cols), |(i, j)| x[(i % h, j % w)])
+}
+
+/// Scale up a grid, each cell becoming a `factor` × `factor` block
+pub fn scale(x: &Array2<i8>, factor: usize) -> Array2<i8> {
+    let (h, w) = x.dim();
+
+    Array2::from_shape_fn((h * factor, w * factor), |(i, j)| x[(i / factor, j / factor)])
+}
+
+/// Let the cells different from `background` fall down their column, keeping their order
+pub fn gravity(x: &Array2<i8>, background: i8) -> Array2<i8> {
+    let mut y = Array2::from_elem(x.dim(), background);
+
+    for (j, column) in x.columns().into_iter().enumerate() {
+        let cells: Vec<i8> = column.iter().copied().filter(|&v| v != background).collect();
+        let offset = x.nrows() - cells.len();
+
+        for (i, v) in cells.into_iter().enumerate() {
+            y[(offset + i, j)] = v;
+        }
+    }
+
+    y
+}
+
+/// Fill the `background` cells from their mirror cell across the vertical axis
+pub fn complete_symmetry_lr(x: &Array2<i8>, background: i8) -> Array2<i8> {
+    let mirror = fliplr(x);
+
+    Array2::from_shape_fn(x.dim(), |at| if x[at] == background { mirror[at] } else { x[at] })
+}
+
+/// Fill the `background` cells from their mirror cell across the horizontal axis
+pub fn complete_symmetry_ud(x: &Array2<i8>, background: i8) -> Array2<i8> {
+    let mirror = flipud(x);
+
+    Array2::from_shape_fn(x.dim(), |at| if x[at] == background { mirror[at] } else { x[at] })
+}
+
 pub fn valid_index(a: &Array2<i8>, at: (isize, isize)) -> bool {
     let (r, c) = at;
  
@@ -256,3 +332,131 @@ pub fn region_set(m: &Array2<i8>, diagonals: bool,) -> HashSet<Vec<(isize, isize
 
     result
 }
+
+/// Cost of the best one-to-one matching of `a` and `b`, a matched pair costing `cost` and an unmatched item
+/// `unmatched`, equal items being paired at no cost; when more than `MAX_MATCHING` items remain, they are all
+/// left unmatched, which bounds the best matching from above on the same scale
+pub fn matching_distance<T: Eq + Hash>(
+    a: &[T],
+    b: &[T],
+    cost: impl Fn(&T, &T) -> f64,
+    unmatched: impl Fn(&T) -> f64,
+) -> f64 {
+    let mut counts: HashMap<&T, usize> = HashMap::new();
+
+    for y in b {
+        *counts.entry(y).or_default() += 1;
+    }
+
+    let a: Vec<&T> = a
+        .iter()
+        .filter(|x| match counts.get_mut(x) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    let b: Vec<&T> = b
+        .iter()
+        .filter(|y| {
+            let count = counts.get_mut(y).expect("Counted item");
+            let rest = *count > 0;
+
+            *count = count.saturating_sub(1);
+            rest
+        })
+        .collect();
+    let n = a.len().max(b.len());
+
+    if n > MAX_MATCHING {
+        return a.iter().chain(&b).map(|x| unmatched(x)).sum();
+    }
+
+    let costs: Vec<Vec<f64> > = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| match (a.get(i), b.get(j)) {
+                    (Some(x), Some(y)) => cost(x, y),
+                    (Some(x), None) => unmatched(x),
+                    (None, Some(y)) => unmatched(y),
+                    (None, None) => 0.0,
+                })
+                .collect()
+        })
+        .collect();
+
+    hungarian(&costs)
+}
+
+/// Minimal total cost of a perfect matching between the rows and columns of a square cost matrix
+/// (Hungarian algorithm with potentials, O(n³))
+pub fn hungarian(costs: &[Vec<f64>]) -> f64 {
+    let n = costs.len();
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    // Row matched to each column, 1-based, 0 for none
+    let mut matched = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+
+    for i in 1..=n {
+        matched[0] = i;
+
+        let mut j0 = 0;
+        let mut min = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+
+        loop {
+            used[j0] = true;
+
+            let i0 = matched[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+
+            for j in 1..=n {
+                if !used[j] {
+                    let cur = costs[i0 - 1][j - 1] - u[i0] - v[j];
+
+                    if cur < min[j] {
+                        min[j] = cur;
+                        way[j] = j0;
+                    }
+
+                    if min[j] < delta {
+                        delta = min[j];
+                        j1 = j;
+                    }
+                }
+            }
+
+            for j in 0..=n {
+                if used[j] {
+                    u[matched[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min[j] -= delta;
+                }
+            }
+
+            j0 = j1;
+
+            if matched[j0] == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let j1 = way[j0];
+
+            matched[j0] = matched[j1];
+            j0 = j1;
+
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    (1..=n).map(|j| costs[matched[j] - 1][j - 1]).sum()
+}
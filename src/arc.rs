@@ -2,14 +2,17 @@ use ndarray::Array2;
 use ndarray::prelude::*;
 use reqwest;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 struct RawTask {
     pub train: Vec<RawPair>,
     pub test: Vec<RawPair>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 struct RawPair {
     pub input: Vec<Vec<i8> >,
     pub output: Option<Vec<Vec<i8> > >,
@@ -29,31 +32,158 @@ pub struct Pairs {
     pub outputs: Vec<Array2<i8> >,
 }
 
-pub async fn load_task(folder: &str, task: &str) -> Result<Task, Box<dyn std::error::Error> > {
-    let url = format!(
-        "https://raw.githubusercontent.com/arcprize/ARC-AGI-2/refs/heads/main/data/{}/{}.json",
-        folder, task
-    );
-    let resp = reqwest::get(&url).await?;
-    let raw_task: RawTask = resp.json().await?;
+/// Source of ARC tasks, addressed by dataset folder (e.g. `training`) and task id
+pub trait TaskSource {
+    async fn load_task(&self, folder: &str, task: &str) -> Result<Task, Box<dyn std::error::Error> >;
+
+    async fn task_ids(&self, folder: &str) -> Result<Vec<String>, Box<dyn std::error::Error> >;
+}
+
+/// Tasks fetched from the ARC-AGI-2 GitHub repository
+#[derive(Clone, Debug)]
+pub struct RemoteSource {
+    pub base_url: String,
+}
+
+impl Default for RemoteSource {
+    fn default() -> Self {
+        Self {
+            base_url: "https://raw.githubusercontent.com/arcprize/ARC-AGI-2/refs/heads/main/data".into(),
+        }
+    }
+}
+
+impl TaskSource for RemoteSource {
+    async fn load_task(&self, folder: &str, task: &str) -> Result<Task, Box<dyn std::error::Error> > {
+        let url = format!("{}/{}/{}.json", self.base_url, folder, task);
+        let resp = reqwest::get(&url).await?.error_for_status()?;
+        let raw_task: RawTask = resp.json().await?;
+
+        task_from_raw(&raw_task)
+    }
+
+    async fn task_ids(&self, folder: &str) -> Result<Vec<String>, Box<dyn std::error::Error> > {
+        let url = format!("{}/{}.txt", self.base_url, folder);
+        let resp = reqwest::get(&url).await?.error_for_status()?;
+        let text = resp.text().await?;
+
+        Ok(text.lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect())
+    }
+}
+
+/// Tasks read from a local copy of the dataset, laid out as `<root>/<folder>/<task>.json`
+#[derive(Clone, Debug)]
+pub struct DirectorySource {
+    pub root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+        }
+    }
+}
+
+impl TaskSource for DirectorySource {
+    async fn load_task(&self, folder: &str, task: &str) -> Result<Task, Box<dyn std::error::Error> > {
+        load_task_file(self.root.join(folder).join(format!("{}.json", task)))
+    }
+
+    async fn task_ids(&self, folder: &str) -> Result<Vec<String>, Box<dyn std::error::Error> > {
+        let mut ids = Vec::new();
+
+        for entry in fs::read_dir(self.root.join(folder))? {
+            let path = entry?.path();
+
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Some(stem) = path.file_stem() {
+                    ids.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+
+        ids.sort();
+
+        Ok(ids)
+    }
+}
+
+/// Tasks read from a combined challenges file (task id → task) and an optional solutions file (task id → test outputs),
+/// as distributed for the ARC Prize competition; the folder argument is ignored
+#[derive(Clone, Debug)]
+pub struct CombinedSource {
+    challenges: HashMap<String, RawTask>,
+    solutions: HashMap<String, Vec<Vec<Vec<i8> > > >,
+}
+
+impl CombinedSource {
+    pub fn new(challenges: impl AsRef<Path>, solutions: Option<&Path>) -> Result<Self, Box<dyn std::error::Error> > {
+        let challenges = serde_json::from_str(&fs::read_to_string(challenges)?)?;
+        let solutions = match solutions {
+            Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            challenges,
+            solutions,
+        })
+    }
+}
+
+impl TaskSource for CombinedSource {
+    async fn load_task(&self, _folder: &str, task: &str) -> Result<Task, Box<dyn std::error::Error> > {
+        let mut raw_task = self.challenges
+            .get(task)
+            .ok_or_else(|| format!("Task {} not found", task))?
+            .clone();
+
+        if let Some(outputs) = self.solutions.get(task) {
+            for (ex, out) in raw_task.test.iter_mut().zip(outputs) {
+                ex.output.get_or_insert_with(|| out.clone());
+            }
+        }
+
+        task_from_raw(&raw_task)
+    }
+
+    async fn task_ids(&self, _folder: &str) -> Result<Vec<String>, Box<dyn std::error::Error> > {
+        let mut ids: Vec<String> = self.challenges.keys().cloned().collect();
+
+        ids.sort();
+
+        Ok(ids)
+    }
+}
+
+/// Load a task from a single ARC JSON file
+pub fn load_task_file(path: impl AsRef<Path>) -> Result<Task, Box<dyn std::error::Error> > {
+    let raw_task: RawTask = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+    task_from_raw(&raw_task)
+}
+
+fn grid(rows: &[Vec<i8> ]) -> Result<Array2<i8>, Box<dyn std::error::Error> > {
+    let ncols = rows.first().map_or(0, Vec::len);
+
+    Ok(Array2::from_shape_vec(
+        (rows.len(), ncols),
+        rows.iter().flatten().cloned().collect(),
+    )?)
+}
+
+fn task_from_raw(raw_task: &RawTask) -> Result<Task, Box<dyn std::error::Error> > {
     let mut task = Task {
         train: Vec::new(),
         test: Vec::new(),
     };
 
     for ex in &raw_task.train {
-        let arr_input = Array2::from_shape_vec(
-            (ex.input.len(), ex.input[0].len()),
-            ex.input.iter().flatten().cloned().collect(),
-        ).unwrap();
+        let arr_input = grid(&ex.input)?;
 
         if let Some(ref out) = ex.output {
-            let arr_output = Array2::from_shape_vec(
-                (out.len(), out[0].len()),
-                out.iter().flatten().cloned().collect(),
-            ).unwrap();
-
-            task.train.push((arr_input, arr_output));
+            task.train.push((arr_input, grid(out)?));
         }
         else {
             task.train.push((arr_input, Array2::default((0, 0))));
@@ -61,18 +191,10 @@ pub async fn load_task(folder: &str, task: &str) -> Result<Task, Box<dyn std::er
     }
 
     for ex in &raw_task.test {
-        let arr_input = Array2::from_shape_vec(
-            (ex.input.len(), ex.input[0].len()),
-            ex.input.iter().flatten().cloned().collect(),
-        ).unwrap();
+        let arr_input = grid(&ex.input)?;
 
         if let Some(ref out) = ex.output {
-            let arr_output = Array2::from_shape_vec(
-                (out.len(), out[0].len()),
-                out.iter().flatten().cloned().collect(),
-            ).unwrap();
-
-            task.test.push((arr_input, Some(arr_output)));
+            task.test.push((arr_input, Some(grid(out)?)));
         }
         else {
             task.test.push((arr_input, None));
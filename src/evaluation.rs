@@ -0,0 +1,234 @@
+use itertools::Itertools;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::airs::{ColorDistance, Connection, GridMetric, OutputCache, SearchConfig, SearchObserver, Strategy};
+use crate::arc::{Task, TaskSource};
+use crate::solver::{self, Solution};
+
+/// Search limits applied to each task
+#[derive(Clone, Debug)]
+pub struct Budget {
+    pub max_level: usize,
+    pub eps: f64,
+    pub time: Option<Duration>,
+    pub evaluations: Option<usize>,
+    pub top_k: usize,
+    pub strategy: Strategy,
+    /// Sketches parsed by `solver::parse_sketch`, the same for every task
+    pub sketches: Vec<Arc<Connection> >,
+    pub grid_metric: Arc<dyn GridMetric>,
+    pub leave_one_out: bool,
+    /// Memory cap in bytes of the output cache of each search, no cache if `None`
+    pub cache_size: Option<usize>,
+    pub observer: Option<Arc<dyn SearchObserver> >,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            max_level: 2,
+            eps: 1e-6,
+            time: None,
+            evaluations: None,
+            top_k: 1,
+            strategy: Strategy::default(),
+            sketches: Vec::new(),
+            grid_metric: Arc::new(ColorDistance),
+            leave_one_out: false,
+            cache_size: None,
+            observer: None,
+        }
+    }
+}
+
+/// Outcome of the search on one task
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskReport {
+    pub task: String,
+    pub solved: bool,
+    pub program: String,
+    pub cost: f64,
+    pub level: usize,
+    pub time: f64,
+    pub nodes: usize,
+    pub error: Option<String>,
+    pub failed_pairs: Vec<usize>,
+}
+
+impl TaskReport {
+    /// Report of a task that could not be searched, such as a task failing to load
+    pub fn failed(task: &str, error: String) -> Self {
+        Self {
+            task: task.to_string(),
+            solved: false,
+            program: String::new(),
+            cost: f64::INFINITY,
+            level: 0,
+            time: 0.0,
+            nodes: 0,
+            error: Some(error),
+            failed_pairs: Vec::new(),
+        }
+    }
+}
+
+/// Outcome of the search on a set of tasks
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    pub tasks: Vec<TaskReport>,
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+impl Report {
+    pub fn solved(&self) -> usize {
+        self.tasks.iter().filter(|t| t.solved).count()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("task,solved,program,cost,level,time,nodes,error,failed_pairs\n");
+
+        for t in &self.tasks {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&t.task),
+                t.solved,
+                csv_field(&t.program),
+                t.cost,
+                t.level,
+                t.time,
+                t.nodes,
+                csv_field(t.error.as_deref().unwrap_or("")),
+                t.failed_pairs.iter().join(" "),
+            ));
+        }
+
+        csv
+    }
+
+    /// Write the report as CSV if the path has a `csv` extension, as JSON otherwise
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error> > {
+        let path = path.as_ref();
+
+        if path.extension().is_some_and(|e| e == "csv") {
+            fs::write(path, self.to_csv())?;
+        } else {
+            fs::write(path, serde_json::to_string_pretty(self)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Search a task with increasing levels until it is solved on the train pairs or the budget is exhausted,
+/// then check the predictions of the best program over all levels against the expected test outputs
+pub fn evaluate_task(id: &str, task: &Task, budget: &Budget) -> (TaskReport, Solution) {
+    let start = Instant::now();
+    let mut nodes = 0;
+    let mut best: Option<(usize, Solution)> = None;
+
+    for level in 1..=budget.max_level.max(1) {
+        let mut config = SearchConfig::new(level, budget.eps)
+            .with_top_k(budget.top_k)
+            .with_strategy(budget.strategy)
+            .with_grid_metric(Arc::clone(&budget.grid_metric));
+
+        // Neurons are created anew for each search, so a cache would not be hit across levels
+        if let Some(cache_size) = budget.cache_size {
+            config = config.with_cache(Arc::new(OutputCache::new(cache_size)));
+        }
+
+        for sketch in &budget.sketches {
+            config = config.with_sketch(Arc::clone(sketch));
+        }
+
+        if let Some(observer) = &budget.observer {
+            config = config.with_observer(Arc::clone(observer));
+        }
+
+        if budget.leave_one_out {
+            config = config.with_leave_one_out(solver::leave_one_out(task));
+        }
+
+        if let Some(time) = budget.time {
+            config = config.with_deadline(start + time);
+        }
+
+        if let Some(evaluations) = budget.evaluations {
+            config = config.with_max_evaluations(evaluations.saturating_sub(nodes));
+        }
+
+        let solution = solver::solve(task, &config);
+
+        nodes += solution.evaluations;
+
+        let done = solution.cost < budget.eps || solution.exhausted;
+
+        if best.as_ref().is_none_or(|(_, b)| solution.cost < b.cost) {
+            best = Some((level, solution));
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    let (level, solution) = best.expect("At least one level is searched");
+    let expected: Option<Vec<_> > = task.test.iter().map(|(_, output)| output.clone()).collect();
+
+    let report = TaskReport {
+        task: id.to_string(),
+        solved: expected.is_some_and(|e| e == solution.predictions),
+        program: solution.program.as_ref().map(|p| p.to_string()).unwrap_or_default(),
+        cost: solution.cost,
+        level,
+        time: start.elapsed().as_secs_f64(),
+        nodes,
+        error: solution.program.as_ref().err().map(|e| e.to_string()),
+        failed_pairs: solution.failures.clone(),
+    };
+
+    (report, solution)
+}
+
+/// Evaluate every task of a folder, calling `on_task` after each one with the task and its solution
+/// unless it failed to load; a task failing to load is reported with its error and the others evaluated
+pub async fn evaluate(
+    source: &impl TaskSource,
+    folder: &str,
+    budget: &Budget,
+    mut on_task: impl FnMut(&TaskReport, Option<(&Task, &Solution)>),
+) -> Result<Report, Box<dyn std::error::Error> > {
+    let mut report = Report::default();
+
+    for id in source.task_ids(folder).await? {
+        let task_report = match source.load_task(folder, &id).await {
+            Ok(task) => {
+                let (task_report, solution) = evaluate_task(&id, &task, budget);
+
+                on_task(&task_report, Some((&task, &solution)));
+                task_report
+            }
+            Err(e) => {
+                let task_report = TaskReport::failed(&id, e.to_string());
+
+                on_task(&task_report, None);
+                task_report
+            }
+        };
+
+        report.tasks.push(task_report);
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,154 @@
+use ndarray::Array2;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::airs;
+use crate::airs::{Neuron, NeuronRegistry, NeuronValue, ValueType};
+use crate::primitives::*;
+
+/// Largest side of an ARC grid
+const MAX_SIZE: usize = 30;
+
+/// Factor of a tiling or a scaling keeping the grid within the ARC size
+fn factor(grid: &Array2<i8>, n: i8, axis: usize) -> Option<usize> {
+    usize::try_from(n)
+        .ok()
+        .filter(|&n| n > 0 && grid.shape()[axis] * n <= MAX_SIZE)
+}
+
+/// Geometric, color and region neurons on a single grid, searched on each example of a task by
+/// `Brain::search_examples`; region neurons apply to every region of the grid
+pub fn example_neurons() -> Vec<Arc<Neuron> > {
+    type Grid = Array2<i8>;
+    type LocationPairs = Vec<Vec<((isize, isize), (isize, isize))> >;
+    type Regions = Vec<Vec<Vec<(isize, isize)> > >;
+
+    vec![
+        Arc::new(Neuron::from_fn("fliplr", |a: Grid| airs::fliplr(&a))),
+        Arc::new(Neuron::from_fn("flipud", |a: Grid| airs::flipud(&a))),
+        Arc::new(Neuron::from_fn("rot90", |a: Grid| rotate(&a, 1))),
+        Arc::new(Neuron::from_fn("rot180", |a: Grid| rotate(&a, 2))),
+        Arc::new(Neuron::from_fn("rot270", |a: Grid| rotate(&a, 3))),
+        Arc::new(Neuron::from_fn("transpose", |a: Grid| airs::transpose(&a))),
+        Arc::new(Neuron::from_fn("crop", |a: Grid| airs::crop(&a, 0))),
+        Arc::new(Neuron::from_fn("gravity_down", |a: Grid| gravity(&a, 0))),
+        Arc::new(Neuron::from_fn("gravity_right", |a: Grid| gravity(&a, 1))),
+        Arc::new(Neuron::from_fn("gravity_up", |a: Grid| gravity(&a, 2))),
+        Arc::new(Neuron::from_fn("gravity_left", |a: Grid| gravity(&a, 3))),
+        Arc::new(Neuron::from_fn("complete_symmetry_lr", |a: Grid| complete_symmetry(&a, true))),
+        Arc::new(Neuron::from_fn("complete_symmetry_ud", |a: Grid| complete_symmetry(&a, false))),
+        Arc::new(Neuron::from_fn("tile", |a: Grid, rows: i8, cols: i8| {
+            Some(airs::tile(&a, factor(&a, rows, 0)?, factor(&a, cols, 1)?))
+        })),
+        Arc::new(Neuron::from_fn("scale", |a: Grid, n: i8| {
+            Some(airs::scale(&a, factor(&a, n, 0).min(factor(&a, n, 1))?))
+        })),
+        Arc::new(Neuron::from_fn("replace_color", |a: Grid, from: i8, to: i8| replace_color(&a, from, to))),
+        Arc::new(Neuron::from_fn("infer_color_mapping", |a: Vec<(Array2<i8>, Array2<i8>)>| infer_color_mapping(&a))),
+        Arc::new(Neuron::from_fn("map", |a: Grid, mapping: HashMap<i8, i8>| airs::map(&a, &mapping))),
+        Arc::new(Neuron::from_fn("fill_regions", |a: Grid, regions: Regions, value: i8| {
+            fill_regions(&a, &regions.concat(), value)
+        })),
+        Arc::new(Neuron::from_fn(
+            "segments",
+            |a: Grid, pairs: LocationPairs, value: i8, start: bool, finish: bool| {
+                segments(&a, &pairs.concat(), value, start, finish)
+            },
+        )),
+        Arc::new(Neuron::from_fn("same_element", |pairs: LocationPairs, first: bool| {
+            same_element(&pairs, first)
+        })),
+        Arc::new(Neuron::from_fn("region_pairs", |regions: Regions| region_pairs(&regions))),
+    ]
+}
+
+/// Neuron `map_<name>` applying `neuron` item by item to lists of its input types; polymorphic if `neuron`
+/// is, it maps `neuron` over lists of any item type
+pub fn map_list(neuron: &Arc<Neuron>) -> Arc<Neuron> {
+    let item_neuron = Arc::clone(neuron);
+    let output_type = neuron.output_type().clone();
+    let function = move |inputs: &[NeuronValue]| {
+        let columns: Vec<Vec<NeuronValue> > = inputs.iter().map(NeuronValue::items).collect::<Option<_> >()?;
+        let n = columns.first()?.len();
+
+        if columns.iter().any(|c| c.len() != n) {
+            return None;
+        }
+
+        let outputs: Vec<NeuronValue> = (0..n)
+            .map(|i| item_neuron.apply(&columns.iter().map(|c| c[i].clone()).collect::<Vec<_> >()))
+            .collect::<Option<_> >()?;
+        let item_type = outputs.first().map_or_else(|| output_type.clone(), NeuronValue::value_type);
+
+        Some(NeuronValue::list(item_type, outputs))
+    };
+
+    Arc::new(Neuron::new(
+        format!("map_{}", neuron.name()),
+        RwLock::new(Arc::new(function)),
+        neuron.input_types().iter().cloned().map(ValueType::list).collect(),
+        ValueType::list(neuron.output_type().clone()),
+    ))
+}
+
+/// Polymorphic neurons on values and lists of any type, instantiated by the search at the types of the
+/// other neurons: `identity`, `first`, `last`, `zip`, and `map_first` and `map_last` taking the first and
+/// last items of lists of lists
+pub fn generic_neurons() -> Vec<Arc<Neuron> > {
+    let t = || ValueType::var("T");
+    let list = |t: ValueType| ValueType::List(Box::new(t));
+    let generic = |name: &str, f: fn(&[NeuronValue]) -> Option<NeuronValue>, input_types, output_type| {
+        Arc::new(Neuron::new(name, RwLock::new(Arc::new(f)), input_types, output_type))
+    };
+
+    let first = generic("first", |inputs| inputs.first()?.items()?.into_iter().next(), vec![list(t())], t());
+    let last = generic("last", |inputs| inputs.first()?.items()?.into_iter().last(), vec![list(t())], t());
+
+    vec![
+        generic("identity", |inputs| inputs.first().cloned(), vec![t()], t()),
+        Arc::clone(&first),
+        Arc::clone(&last),
+        generic(
+            "zip",
+            |inputs| {
+                let [a, b] = inputs else {
+                    return None;
+                };
+                let item_type = ValueType::Tuple(vec![a.value_type().item_type()?, b.value_type().item_type()?]);
+                let items = a
+                    .items()?
+                    .into_iter()
+                    .zip(b.items()?)
+                    .map(|(x, y)| NeuronValue::Tuple(vec![x, y]))
+                    .collect();
+
+                Some(NeuronValue::list(item_type, items))
+            },
+            vec![list(ValueType::var("A")), list(ValueType::var("B"))],
+            list(ValueType::Tuple(vec![ValueType::var("A"), ValueType::var("B")])),
+        ),
+        map_list(&first),
+        map_list(&last),
+    ]
+}
+
+/// Constant neurons: `false`, `true` and the colors `0` to `9` as `Int8`
+pub fn constant_neurons() -> Vec<Arc<Neuron> > {
+    let booleans = [false, true]
+        .into_iter()
+        .map(|b| Arc::new(Neuron::from_fn(b.to_string(), move || b)));
+    let digits = (0..10).map(|i: i8| Arc::new(Neuron::from_fn(i.to_string(), move || i)));
+
+    booleans.chain(digits).collect()
+}
+
+/// Registry of the single-grid and constant neurons, for searches on each example of a task
+pub fn example_registry() -> NeuronRegistry {
+    let mut registry = NeuronRegistry::new();
+
+    registry
+        .register_all(example_neurons().into_iter().chain(constant_neurons()))
+        .expect("Library neuron names are unique");
+
+    registry
+}
@@ -1,95 +1,357 @@
 mod airs;
 mod arc;
+mod evaluation;
+mod library;
 mod primitives;
+mod solver;
+mod submission;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use airs::{CellMismatch, ColorDistance, ColorIoU, EditDistance, ObjectDistance, SearchObserver, SearchStats, Strategy};
+use arc::{CombinedSource, DirectorySource, RemoteSource, TaskSource};
+use evaluation::Budget;
+use submission::Submission;
+
+const USAGE: &str = "Usage:
+    airs solve <task.json> [options]
+    airs solve-dir <dir | challenges.json | remote:folder> [options]
+    airs eval <dir | challenges.json | remote:folder> [options]
+
+    A remote:folder source fetches the tasks of a folder of the ARC-AGI-2 repository, e.g. remote:evaluation
+
+Options:
+    --max-level <n>          Maximum depth of the searched connections (default: 2)
+    --eps <x>                Heuristic cost under which a program is accepted (default: 1e-6)
+    --time-limit <s>         Time budget of the search of each task, in seconds
+    --max-evaluations <n>    Budget of evaluated candidates for the search of each task
+    --top-k <n>              Number of ranked programs kept per task for the submission attempts (default: 1; at least 2 with --submission, 8 with leave-one-out validation)
+    --strategy <name>        Enumeration strategy: exhaustive, equivalence or best-first (default: exhaustive)
+    --metric <name>          Grid distance: colors, cells, iou, edit or objects (default: colors)
+    --validation <mode>      Rank the programs solving the train pairs by generalization: none or leave-one-out (default: none)
+    --cache-size <MB>        Memoize the outputs of shared sub-programs up to this memory per search
+    --progress <s>           Print the search statistics at each level and at most every s seconds meanwhile
+    --sketch <program>       Only search programs of this shape, holes written as types, e.g. map(input, Map); repeatable
+    --solutions <file>       Solutions file matching a combined challenges file
+    --submission <file>      Write the test predictions as an ARC Prize submission file
+    --report <file>          Write the evaluation report as CSV (.csv extension) or JSON";
+
+/// Ranked programs kept per task at least when validated, for leave-one-out to tell apart those solving the
+/// train pairs
+const VALIDATION_TOP_K: usize = 8;
+
+struct Options {
+    budget: Budget,
+    solutions: Option<PathBuf>,
+    submission: Option<PathBuf>,
+    report: Option<PathBuf>,
+}
 
-use ndarray::Array2;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+fn parse_options(args: &[String]) -> Result<Options, Box<dyn std::error::Error> > {
+    let mut options = Options {
+        budget: Budget::default(),
+        solutions: None,
+        submission: None,
+        report: None,
+    };
+    let mut it = args.iter();
+
+    while let Some(arg) = it.next() {
+        // Read by the arm of a known option only, an unknown one being reported as such
+        let mut value = || it.next().ok_or_else(|| format!("Missing value for {}", arg));
+
+        match arg.as_str() {
+            "--max-level" => options.budget.max_level = value()?.parse()?,
+            "--eps" => options.budget.eps = value()?.parse()?,
+            "--time-limit" => options.budget.time = Some(Duration::from_secs_f64(value()?.parse()?)),
+            "--max-evaluations" => options.budget.evaluations = Some(value()?.parse()?),
+            "--top-k" => options.budget.top_k = value()?.parse()?,
+            "--strategy" => options.budget.strategy = match value()?.as_str() {
+                "exhaustive" => Strategy::Exhaustive,
+                "equivalence" => Strategy::ObservationalEquivalence,
+                "best-first" => Strategy::BestFirst,
+                strategy => return Err(format!("Unknown strategy {}", strategy).into()),
+            },
+            "--metric" => options.budget.grid_metric = match value()?.as_str() {
+                "colors" => Arc::new(ColorDistance),
+                "cells" => Arc::new(CellMismatch),
+                "iou" => Arc::new(ColorIoU),
+                "edit" => Arc::new(EditDistance),
+                "objects" => Arc::new(ObjectDistance::default()),
+                metric => return Err(format!("Unknown metric {}", metric).into()),
+            },
+            "--validation" => options.budget.leave_one_out = match value()?.as_str() {
+                "none" => false,
+                "leave-one-out" => true,
+                validation => return Err(format!("Unknown validation {}", validation).into()),
+            },
+            "--cache-size" => options.budget.cache_size = Some((value()?.parse::<f64>()? * 1e6) as usize),
+            "--progress" => {
+                options.budget.observer = Some(Arc::new(ProgressPrinter::new(Duration::from_secs_f64(value()?.parse()?))))
+            }
+            "--sketch" => options.budget.sketches.push(solver::parse_sketch(value()?)?),
+            "--solutions" => options.solutions = Some(value()?.into()),
+            "--submission" => options.submission = Some(value()?.into()),
+            "--report" => options.report = Some(value()?.into()),
+            _ => return Err(format!("Unknown option {}", arg).into()),
+        }
+    }
 
-use airs::Neuron as Neuron;
-use airs::NeuronFn as NeuronFn;
-use airs::ValueType as ValueType;
+    // A submission has two attempts per test input
+    if options.submission.is_some() {
+        options.budget.top_k = options.budget.top_k.max(2);
+    }
 
-use crate::airs::NeuronValue;
+    if options.budget.leave_one_out {
+        options.budget.top_k = options.budget.top_k.max(VALIDATION_TOP_K);
+    }
 
-fn update_region_neurons(region_neurons: &mut HashMap<i8, Arc<Neuron> >, pairs: &Vec<Array2<i8> >) {
-    let mut region_map: HashMap<i8, Vec<Vec<Vec<(isize, isize)> > > > = HashMap::new();
+    Ok(options)
+}
 
-    for input_ in pairs {
-        let s = airs::region_set(input_, false);
+/// Search statistics printed to stderr at the end of each level and of the search, and at most every
+/// `interval` on progress or on a better candidate
+#[derive(Debug)]
+struct ProgressPrinter {
+    interval: Duration,
+    last: Mutex<Instant>,
+}
 
-        let mut regions: HashMap<i8, Vec<Vec<(isize, isize)> > > = (0..10)
-            .map(|i| (i, Vec::new()))
-            .collect();
+impl ProgressPrinter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn print(&self, event: &str, stats: &SearchStats) {
+        let cache = stats
+            .cache
+            .map(|c| format!(", cache hit rate {:.1}%", 100.0 * c.hit_rate()))
+            .unwrap_or_default();
+
+        eprintln!(
+            "{}: {} evaluations ({:.0}/s){}, best {} (cost {})",
+            event,
+            stats.evaluations,
+            stats.evaluations_per_second(),
+            cache,
+            stats.best_program.as_deref().unwrap_or("none"),
+            stats.best_cost,
+        );
+    }
+
+    fn print_throttled(&self, event: &str, stats: &SearchStats) {
+        let mut last = self.last.lock().expect("Lock poisoned");
 
-        for r in s {
-            let value = input_[(r[0].0 as usize, r[0].1 as usize)];
-            regions.get_mut(&value).unwrap().push(r);
+        if last.elapsed() >= self.interval {
+            *last = Instant::now();
+            self.print(event, stats);
         }
+    }
+}
+
+impl SearchObserver for ProgressPrinter {
+    fn on_level(&self, level: usize, stats: &SearchStats) {
+        self.print(&format!("level {} ({} connections)", level, stats.connections_per_level[level]), stats);
+    }
+
+    fn on_best(&self, stats: &SearchStats) {
+        self.print_throttled("better candidate", stats);
+    }
+
+    fn on_progress(&self, stats: &SearchStats) {
+        self.print_throttled("progress", stats);
+    }
+
+    fn on_finish(&self, stats: &SearchStats) {
+        self.print(&format!("done in {:.3}s", stats.elapsed.as_secs_f64()), stats);
+    }
+}
 
-        for (&k, v) in &regions {
-            let l = region_map.entry(k).or_insert_with(Vec::new);
-            l.push(v.clone());
+fn print_solution(name: &str, solution: &solver::Solution) {
+    match &solution.program {
+        Ok(program) => println!("{}: {} (cost {})", name, program.to_string(), solution.cost),
+        Err(e) => println!("{}: {}", name, e),
+    }
+
+    for (i, grid) in solution.predictions.iter().enumerate() {
+        println!("test {}:\n{}", i, grid);
+    }
+}
+
+async fn solve_source(
+    source: &impl TaskSource,
+    folder: &str,
+    options: &Options,
+    evaluate: bool,
+) -> Result<(), Box<dyn std::error::Error> > {
+    let mut submission = Submission::new();
+
+    let report = evaluation::evaluate(source, folder, &options.budget, |task_report, outcome| {
+        let Some((task, solution)) = outcome else {
+            println!("{}: {}", task_report.task, task_report.error.as_deref().unwrap_or_default());
+            return;
+        };
+
+        print_solution(&task_report.task, solution);
+        submission.add(&task_report.task, task, &solution.candidates);
+
+        if evaluate {
+            println!(
+                "{}: {} (level {}, {} nodes, {:.3}s)",
+                task_report.task,
+                if task_report.solved { "solved" } else { "unsolved" },
+                task_report.level,
+                task_report.nodes,
+                task_report.time,
+            );
         }
+    }).await?;
+
+    if evaluate {
+        println!("{}/{} tasks solved", report.solved(), report.tasks.len());
     }
 
-    for (&k, v) in &region_map {
-        let function: Arc<NeuronFn> = Arc::new({
-            let v = v.clone();
+    if let Some(path) = &options.report {
+        report.write(path)?;
+    }
 
-            move |_| Some(NeuronValue::RegionsList(v.clone()))
-        });
+    if let Some(path) = &options.submission {
+        submission.write(path)?;
+    }
+
+    Ok(())
+}
 
-        let empty_count = v.iter().filter(|l| l.is_empty()).count();
+async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error> > {
+    let (command, path) = match args {
+        [command, path, ..] => (command.as_str(), path),
+        _ => return Err(USAGE.into()),
+    };
+    let options = parse_options(&args[2..])?;
 
-        match region_neurons.get_mut(&k) {
-            Some(neuron) => {
-                if empty_count != v.len() {
-                    let mut func = neuron.function.write().unwrap();
-                    *func = function;
-                } else {
-                    region_neurons.remove(&k);
-                }
+    match command {
+        "solve" => {
+            let task = arc::load_task_file(path)?;
+            let id = Path::new(path).file_stem().map_or(path.clone(), |s| s.to_string_lossy().into_owned());
+            let (_, solution) = evaluation::evaluate_task(&id, &task, &options.budget);
+
+            print_solution(path, &solution);
+
+            if let Some(submission_path) = &options.submission {
+                let mut submission = Submission::new();
+
+                submission.add(&id, &task, &solution.candidates);
+                submission.write(submission_path)?;
             }
-            None => {
-                if empty_count != v.len() {
-                    region_neurons.insert(
-                        k,
-                        Arc::new(Neuron::new(
-                            format!("region{}", k),
-                            RwLock::new(function),
-                            vec![],
-                            ValueType::RegionsList
-                        )),
-                    );
-                }
+        }
+        "solve-dir" | "eval" => {
+            let evaluate = command == "eval";
+
+            if let Some(folder) = path.strip_prefix("remote:") {
+                solve_source(&RemoteSource::default(), folder, &options, evaluate).await?;
+            } else if Path::new(path).is_file() {
+                let source = CombinedSource::new(path, options.solutions.as_deref())?;
+
+                solve_source(&source, "", &options, evaluate).await?;
+            } else {
+                solve_source(&DirectorySource::new(path), "", &options, evaluate).await?;
             }
         }
+        _ => return Err(USAGE.into()),
     }
+
+    Ok(())
 }
 
-#[cfg(not(test))]
-fn main() {
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Err(e) = run(&args).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
 
 #[cfg(test)]
 mod tests
 {
+    use ndarray::{array, Array2};
     use std::collections::{HashMap, HashSet};
     use std::hash::{DefaultHasher, Hash, Hasher};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::{Arc, RwLock};
+    use std::time::Duration;
 
     use super::airs::Brain as Brain;
+    use super::airs::CancellationToken as CancellationToken;
     use super::airs::Connection as Connection;
     use super::airs::ConnectionValue as ConnectionValue;
+    use super::airs::Context as Context;
     use super::airs::Neuron as Neuron;
     use super::airs::ValueType as ValueType;
     use super::airs::NeuronValue as NeuronValue;
-
-    use super::arc::load_task;
+    use super::airs::SearchConfig as SearchConfig;
+    use super::airs::SearchError as SearchError;
+    use super::airs::ParseError as ParseError;
+    use super::airs::parse_connection;
+    use super::airs::NeuronRegistry as NeuronRegistry;
+    use super::airs::RegistryError as RegistryError;
+    use super::airs::Strategy as Strategy;
+
+    use super::arc::CombinedSource;
+    use super::arc::DirectorySource;
+    use super::arc::TaskSource;
     use super::arc::input_output_pairs;
     use super::arc::input_option_output_pairs;
+    use super::arc::Task;
+    use super::evaluation::{evaluate, evaluate_task, Budget, Report};
+    use super::library;
     use super::primitives::*;
+    use super::submission::{Attempts, Submission};
+    use super::parse_options;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// `false`, `true` and the digits, as `Int8`, with their checked sum `add`, whose calls are counted in `calls`
+    fn digit_sums(calls: &Arc<AtomicUsize>) -> Vec<Arc<Neuron> > {
+        let calls = Arc::clone(calls);
+        let mut neurons = library::constant_neurons();
+
+        neurons.push(Arc::new(Neuron::from_fn("add", move |a: i8, b: i8| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            a.checked_add(b)
+        })));
+
+        neurons
+    }
+
+    fn data_source() -> DirectorySource {
+        DirectorySource::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data"))
+    }
+
+    /// Check that `value` given to a neuron is printed as a literal parsing back to it
+    fn assert_literal_round_trip(value: &NeuronValue) {
+        let neuron = Arc::new(Neuron::new(
+            "take",
+            RwLock::new(Arc::new(|_inputs: &[NeuronValue]| Some(NeuronValue::Bool(true)))),
+            vec![value.value_type()],
+            ValueType::Bool,
+        ));
+        let registry = NeuronRegistry::try_from(vec![neuron.clone()]).unwrap();
+        let connection = Connection::new(neuron, &[ConnectionValue::Value(value.clone())]);
+        let program = connection.to_string();
+
+        assert_eq!(parse_connection(&program, &registry).map(|c| c.inputs()), Ok(connection.inputs()), "{}", program);
+    }
 
     #[test]
     fn test_valid_connections() {
@@ -245,6 +507,194 @@ mod tests
         assert_eq!(connections.len(), 1);
     }
 
+    #[test]
+    fn test_parse() {
+        let mut neurons: Vec<Arc<Neuron> > = vec![];
+
+        for i in 0..10 {
+            neurons.push(Arc::new(Neuron::new(
+                format!("{}", i),
+                RwLock::new(Arc::new(move |_inputs: &[NeuronValue]| {
+                    Some(NeuronValue::Int64(i))
+                })),
+                vec![],
+                ValueType::Int64,
+            )));
+        }
+
+        for (name, f) in [("add", (|a, b| a + b) as fn(i64, i64) -> i64), ("mul", |a, b| a * b)] {
+            neurons.push(Arc::new(Neuron::new(
+                name,
+                RwLock::new(Arc::new(move |inputs: &[NeuronValue]| {
+                    match inputs {
+                        [NeuronValue::Int64(a), NeuronValue::Int64(b)] => Some(NeuronValue::Int64(f(*a, *b))),
+                        _ => None,
+                    }
+                })),
+                vec![ValueType::Int64, ValueType::Int64],
+                ValueType::Int64,
+            )));
+        }
+
+        neurons.push(Arc::new(Neuron::new(
+            "is_zero",
+            RwLock::new(Arc::new(|inputs: &[NeuronValue]| {
+                match inputs {
+                    [NeuronValue::Int64(a)] => Some(NeuronValue::Bool(*a == 0)),
+                    _ => None,
+                }
+            })),
+            vec![ValueType::Int64],
+            ValueType::Bool,
+        )));
+
+        let neurons = NeuronRegistry::try_from(neurons).unwrap();
+
+        for program in ["mul(add(2, 3), 4)", "add(Int64, mul(Int64, 7))", "is_zero(add(12, -12))", "5"] {
+            let connection = parse_connection(program, &neurons).unwrap();
+
+            assert_eq!(connection.to_string(), program);
+            assert_eq!(parse_connection(&connection.to_string(), &neurons).unwrap(), connection);
+        }
+
+        let connection = parse_connection(" mul( add(2,3) ,4 ) ", &neurons).unwrap();
+
+        assert_eq!(connection.output(), Some(NeuronValue::Int64(20)));
+        assert_eq!(connection.depth(0), 2);
+
+        // Names of neurons take precedence over literals, the 12 is a literal
+        let connection = parse_connection("add(1, 12)", &neurons).unwrap();
+
+        assert_eq!(
+            connection.inputs(),
+            vec![
+                ConnectionValue::Connection(Arc::new(Connection::new(neurons.get("1").unwrap().clone(), &[]))),
+                ConnectionValue::Value(NeuronValue::Int64(12)),
+            ]
+        );
+
+        let sketch = parse_connection("add(Int64, mul(Int64, 7))", &neurons).unwrap();
+
+        assert_eq!(sketch.holes(), vec![ValueType::Int64, ValueType::Int64]);
+
+        assert_eq!(parse_connection("sub(1, 2)", &neurons), Err(ParseError::UnknownNeuron("sub".into())));
+        assert_eq!(
+            parse_connection("add(1)", &neurons),
+            Err(ParseError::Arity { neuron: "add".into(), expected: 2, found: 1 })
+        );
+        assert_eq!(
+            parse_connection("add(1, 2, 3)", &neurons),
+            Err(ParseError::Arity { neuron: "add".into(), expected: 2, found: 3 })
+        );
+        assert_eq!(
+            parse_connection("add(is_zero(1), 2)", &neurons),
+            Err(ParseError::TypeMismatch { expected: ValueType::Int64, found: ValueType::Bool })
+        );
+        assert_eq!(
+            parse_connection("add(Bool, 2)", &neurons),
+            Err(ParseError::TypeMismatch { expected: ValueType::Int64, found: ValueType::Bool })
+        );
+        assert_eq!(
+            parse_connection("add(x, 2)", &neurons),
+            Err(ParseError::InvalidLiteral { literal: "x".into(), value_type: ValueType::Int64 })
+        );
+        assert_eq!(parse_connection("add(1, 2", &neurons), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse_connection("add(1, 2))", &neurons), Err(ParseError::UnexpectedChar(')', 9)));
+
+        // Literals of every kind parse back to their value, strings and chars being quoted
+        let values = [
+            NeuronValue::Bool(false),
+            NeuronValue::Int8(-3),
+            NeuronValue::UInt16(7),
+            NeuronValue::Index(4),
+            NeuronValue::Double(-1.5),
+            NeuronValue::Float(0.25),
+            NeuronValue::String("say \"hi\", (f) [x]: {y} \\".into()),
+            NeuronValue::String(String::new()),
+            NeuronValue::Char("'".into()),
+            NeuronValue::Grid(array![[1, 2], [3, -4]]),
+            NeuronValue::Grid(Array2::zeros((0, 0))),
+            NeuronValue::Grids(vec![array![[1]], array![[0, 0]]]),
+            NeuronValue::Map(HashMap::from([(1, 2), (3, 4)])),
+            NeuronValue::PairGrids(vec![(array![[1]], array![[2, 3]])]),
+            NeuronValue::LocationPairs(vec![vec![((0, 0), (1, -2))], vec![]]),
+            NeuronValue::RegionsList(vec![vec![vec![(0, 0), (0, 1)]], vec![]]),
+            NeuronValue::list(ValueType::Int64, vec![NeuronValue::Int64(1), NeuronValue::Int64(2)]),
+            NeuronValue::list(ValueType::String, vec![NeuronValue::String("a, b".into())]),
+            NeuronValue::list(ValueType::Bool, vec![]),
+            NeuronValue::Tuple(vec![NeuronValue::Int8(1), NeuronValue::String(")".into())]),
+            NeuronValue::Option(ValueType::Int8, Some(Box::new(NeuronValue::Int8(1)))),
+            NeuronValue::Option(ValueType::Grid, None),
+        ];
+
+        for value in &values {
+            assert_literal_round_trip(value);
+        }
+
+        assert_eq!(
+            parse_connection("is_zero([1, 2])", &neurons),
+            Err(ParseError::UnexpectedChar('[', 8))
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = NeuronRegistry::new();
+
+        let functions: [(&str, Vec<ValueType>, ValueType, fn(&[NeuronValue]) -> Option<NeuronValue>); 5] = [
+            ("1", vec![], ValueType::Int64, |_| Some(NeuronValue::Int64(1))),
+            ("true", vec![], ValueType::Bool, |_| Some(NeuronValue::Bool(true))),
+            ("add", vec![ValueType::Int64, ValueType::Int64], ValueType::Int64, |inputs| match inputs {
+                [NeuronValue::Int64(a), NeuronValue::Int64(b)] => Some(NeuronValue::Int64(a + b)),
+                _ => None,
+            }),
+            ("mul", vec![ValueType::Int64, ValueType::Int64], ValueType::Int64, |inputs| match inputs {
+                [NeuronValue::Int64(a), NeuronValue::Int64(b)] => Some(NeuronValue::Int64(a * b)),
+                _ => None,
+            }),
+            ("is_zero", vec![ValueType::Int64], ValueType::Bool, |inputs| match inputs {
+                [NeuronValue::Int64(a)] => Some(NeuronValue::Bool(*a == 0)),
+                _ => None,
+            }),
+        ];
+
+        for (name, input_types, output_type, function) in functions {
+            registry.register(Arc::new(Neuron::new(
+                name,
+                RwLock::new(Arc::new(function)),
+                input_types,
+                output_type,
+            ))).unwrap();
+        }
+
+        let names = |neurons: Vec<&Arc<Neuron> >| neurons.iter().map(|n| n.name()).collect::<Vec<_> >();
+
+        assert_eq!(registry.len(), 5);
+        assert_eq!(registry.get("mul").map(|n| n.name()), Some("mul".to_string()));
+        assert!(registry.get("sub").is_none());
+        assert_eq!(names(registry.by_output_type(&ValueType::Int64)), ["1", "add", "mul"]);
+        assert_eq!(names(registry.by_output_type(&ValueType::Bool)), ["true", "is_zero"]);
+        assert!(registry.by_output_type(&ValueType::Grids).is_empty());
+        assert_eq!(names(registry.by_input_types(&[ValueType::Int64, ValueType::Int64])), ["add", "mul"]);
+        assert_eq!(names(registry.by_input_types(&[])), ["1", "true"]);
+
+        let duplicate = Arc::new(Neuron::new(
+            "add",
+            RwLock::new(Arc::new(|_inputs: &[NeuronValue]| None)),
+            vec![ValueType::Int64],
+            ValueType::Int64,
+        ));
+
+        assert_eq!(registry.register(duplicate), Err(RegistryError::Duplicate("add".into())));
+        assert_eq!(registry.len(), 5);
+        assert_eq!(registry.by_input_types(&[ValueType::Int64]).len(), 1);
+
+        let brain = Brain::from_registry(&registry);
+        let search = brain.search(&[NeuronValue::Int64(2)], &SearchConfig::new(1, 1e-6));
+
+        assert_eq!(search.connections[0].as_ref().unwrap().to_string(), "add(1, 1)");
+    }
+
     #[test]
     fn test_str() {
         let mut neurons: Vec<Arc<Neuron> > = vec![];
@@ -331,7 +781,7 @@ mod tests
         assert!(conn.output().unwrap().heuristic(&target) == 0.0);
 
         let brain: Brain = Brain::new(neurons);
-        let connections = brain.learn(&[target.clone()].to_vec(), 2, 1e-6);
+        let connections = brain.learn(&[target.clone()].to_vec(), 2, 1e-6).into_iter().collect::<Result<Vec<_>, _> >().unwrap();
 
         assert_ne!(connections.len(), 0);
 
@@ -340,9 +790,1184 @@ mod tests
         assert!(connections[0].output().unwrap().heuristic(&target) == 0.0);
     }
 
+    #[test]
+    fn test_submission() {
+        let task = Task {
+            train: vec![],
+            test: vec![(array![[1, 2]], None), (array![[3]], None)],
+        };
+
+        let candidates = vec![
+            vec![array![[2, 1]], array![[4]]],
+            vec![array![[2, 1]], array![[5]]],
+            vec![array![[1, 1]]],
+        ];
+
+        let mut submission = Submission::new();
+        submission.add("task", &task, &candidates);
+
+        let attempts = &submission.tasks["task"];
+
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0], Attempts { attempt_1: vec![vec![2, 1]], attempt_2: vec![vec![1, 1]] });
+        assert_eq!(attempts[1], Attempts { attempt_1: vec![vec![4]], attempt_2: vec![vec![5]] });
+
+        submission.add("empty", &task, &[]);
+
+        assert_eq!(submission.tasks["empty"][0], Attempts { attempt_1: vec![vec![1, 2]], attempt_2: vec![vec![1, 2]] });
+
+        let json = serde_json::to_value(&submission).unwrap();
+
+        assert_eq!(json["task"][1]["attempt_2"], serde_json::json!([[5]]));
+
+        assert_eq!(parse_options(&args(&["--submission", "submission.json"])).unwrap().budget.top_k, 2);
+        assert_eq!(parse_options(&args(&["--top-k", "3", "--submission", "submission.json"])).unwrap().budget.top_k, 3);
+        assert_eq!(parse_options(&args(&["--top-k", "3"])).unwrap().budget.top_k, 3);
+        assert_eq!(parse_options(&args(&["--top-k"])).err().unwrap().to_string(), "Missing value for --top-k");
+        assert_eq!(parse_options(&args(&["--top"])).err().unwrap().to_string(), "Unknown option --top");
+        assert_eq!(parse_options(&args(&["--top", "3"])).err().unwrap().to_string(), "Unknown option --top");
+    }
+
+    #[test]
+    fn test_search_budget() {
+        let neurons = digit_sums(&Arc::default());
+
+        let target = NeuronValue::Int8(17);
+        let brain: Brain = Brain::new(neurons);
+
+        let search = brain.search(std::slice::from_ref(&target), &SearchConfig::new(2, 1e-6));
+
+        assert!(!search.exhausted);
+        assert_eq!(search.connections[0].as_ref().unwrap().output(), Some(target.clone()));
+
+        let limited = brain.search(std::slice::from_ref(&target), &SearchConfig::new(2, 1e-6).with_max_evaluations(5));
+
+        assert!(limited.exhausted);
+        assert!(limited.evaluations < search.evaluations);
+        assert!(limited.connections[0].as_ref().unwrap().output().unwrap().heuristic(&target).is_finite());
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let cancelled = brain.search(std::slice::from_ref(&target), &SearchConfig::new(2, 1e-6).with_cancellation(cancellation));
+
+        assert!(cancelled.exhausted);
+        assert_eq!(cancelled.connections[0], Err(SearchError::BudgetExceeded));
+
+        let expired = brain.search(std::slice::from_ref(&target), &SearchConfig::new(2, 1e-6).with_timeout(Duration::ZERO));
+
+        assert!(expired.exhausted);
+        assert_eq!(expired.connections[0], Err(SearchError::BudgetExceeded));
+    }
+
+    #[test]
+    fn test_top_k() {
+        let neurons = digit_sums(&Arc::default());
+
+        let brain: Brain = Brain::new(neurons);
+        let targets = [NeuronValue::Int8(16), NeuronValue::Int8(100)];
+        let search = brain.search(&targets, &SearchConfig::new(2, 1e-6).with_top_k(3));
+
+        assert_eq!(search.ranked.len(), 2);
+
+        let exact = &search.ranked[0];
+
+        assert_eq!(exact.len(), 3);
+
+        for (i, pair) in exact.iter().enumerate() {
+            assert_eq!(pair.cost, 0.0);
+            assert_eq!(pair.connection.output(), Some(targets[0].clone()));
+            assert!(exact[..i].iter().all(|other| other.connection != pair.connection));
+        }
+
+        let approximate = &search.ranked[1];
+
+        assert_eq!(approximate.len(), 3);
+        assert_eq!(approximate[0].cost, 64.0);
+        assert!(approximate.windows(2).all(|w| w[0].cost <= w[1].cost));
+        assert_eq!(search.connections[1].as_ref().unwrap(), &approximate[0].connection);
+    }
+
+    #[test]
+    fn test_observational_equivalence() {
+        let neurons = digit_sums(&Arc::default());
+
+        let brain: Brain = Brain::new(neurons);
+        let targets = [NeuronValue::Int8(36), NeuronValue::Int8(100)];
+
+        let exhaustive = brain.search(&targets, &SearchConfig::new(2, 1e-6));
+        let equivalence = brain.search(
+            &targets,
+            &SearchConfig::new(2, 1e-6).with_strategy(Strategy::ObservationalEquivalence),
+        );
+
+        assert!(equivalence.evaluations < exhaustive.evaluations);
+
+        let exact = equivalence.connections[0].as_ref().unwrap();
+
+        assert_eq!(exact.output(), Some(targets[0].clone()));
+        assert_eq!(exact.cost(), exhaustive.connections[0].as_ref().unwrap().cost());
+
+        // Each distinct sum is kept once, so the closest one to 100 is the maximum 36
+        let approximate = &equivalence.ranked[1][0];
+
+        assert_eq!(approximate.cost, 64.0);
+        assert!(!equivalence.exhausted);
+
+        // Ties are broken by cost, connection cost and written program, the same on every run
+        let config = SearchConfig::new(2, 1e-6).with_strategy(Strategy::ObservationalEquivalence).with_top_k(3);
+        let ranked = |search: &super::airs::Search| {
+            search.ranked[0].iter().map(|pair| (pair.cost, pair.connection_cost, pair.connection.to_string())).collect::<Vec<_> >()
+        };
+        let first = ranked(&brain.search(&targets, &config));
+
+        assert!(first.windows(2).all(|w| w[0] <= w[1]), "{:?}", first);
+
+        for _ in 0..4 {
+            assert_eq!(ranked(&brain.search(&targets, &config)), first);
+        }
+    }
+
+    #[test]
+    fn test_best_first() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let brain: Brain = Brain::new(digit_sums(&calls));
+        let targets = [NeuronValue::Int8(100)];
+        let config = SearchConfig::new(4, 1e-6)
+            .with_strategy(Strategy::BestFirst)
+            .with_max_evaluations(100_000);
+        let search = brain.search(&targets, &config);
+
+        // Connections popped from the frontier keep their value, only the 12 constants are evaluated without `add`
+        assert_eq!(calls.load(Ordering::Relaxed), search.evaluations - 12);
+
+        // Sums of depth 3 are at most 72, so 100 needs a connection of depth 4
+        let connection = search.connections[0].as_ref().unwrap();
+
+        assert_eq!(connection.output(), Some(targets[0].clone()));
+        assert_eq!(connection.depth(0), 4);
+        assert!(!search.exhausted);
+
+        // 4 is first reached from 9, whose value is closer to 10, by five `dec`, then from 0 by `add4`
+        let brain: Brain = Brain::new(vec![
+            Arc::new(Neuron::from_fn("nine", || 9i8)),
+            Arc::new(Neuron::from_fn("zero", || 0i8)),
+            Arc::new(Neuron::from_fn("dec", |a: i8| a - 1)),
+            Arc::new(Neuron::from_fn("add4", |a: i8| a + 4)),
+        ]);
+        let config = SearchConfig::new(6, 1e-6).with_strategy(Strategy::BestFirst).with_top_k(100);
+        let search = brain.search(&[NeuronValue::Int8(10)], &config);
+        let fours: Vec<String> = search.ranked[0]
+            .iter()
+            .filter(|pair| pair.connection.output() == Some(NeuronValue::Int8(4)))
+            .map(|pair| pair.connection.to_string())
+            .collect();
+
+        assert_eq!(fours, ["add4(zero)"]);
+    }
+
+    #[tokio::test]
+    async fn test_best_first_task() -> Result<(), Box<dyn std::error::Error> > {
+        let task = data_source().load_task("training", "0d3d703e").await?;
+        let config = SearchConfig::new(2, 1e-6).with_strategy(Strategy::BestFirst);
+        let solution = super::solver::solve(&task, &config);
+
+        assert_eq!(solution.cost, 0.0);
+        assert_eq!(Some(solution.predictions), task.test.into_iter().map(|(_, output)| output).collect());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sketch() {
+        let registry = NeuronRegistry::try_from(digit_sums(&Arc::default())).unwrap();
+        let add_neuron = registry.get("add").unwrap();
+        let hole = ConnectionValue::Value(NeuronValue::ValueType(ValueType::Int8));
+        let one = ConnectionValue::Connection(Arc::new(Connection::new(registry.get("1").unwrap().clone(), &[])));
+        let inner = ConnectionValue::Connection(Arc::new(Connection::new(add_neuron.clone(), &[hole.clone(), hole])));
+        let sketch = Arc::new(Connection::new(add_neuron.clone(), &[one, inner]));
+
+        assert_eq!(sketch.holes(), vec![ValueType::Int8, ValueType::Int8]);
+
+        let two = ConnectionValue::Value(NeuronValue::Int8(2));
+        let filled = sketch.fill_holes(&[two.clone(), two]);
+
+        assert_eq!(filled.to_string(), "add(1, add(2, 2))");
+        assert_eq!(filled.output(), Some(NeuronValue::Int8(5)));
+        assert_eq!(sketch.holes().len(), 2);
+
+        let brain: Brain = Brain::from_registry(&registry);
+        let targets = [NeuronValue::Int8(20)];
+        let config = SearchConfig::new(0, 1e-6).with_sketch(sketch);
+        let search = brain.search(&targets, &config);
+        let connection = search.connections[0].as_ref().unwrap();
+
+        // Only the holes are searched: with the first input kept to 1, the closest sum is 1 + 9 + 9
+        assert!(connection.to_string().starts_with("add(1, add("));
+        assert_eq!(connection.output(), Some(NeuronValue::Int8(19)));
+        assert_eq!(search.ranked[0][0].cost, 1.0);
+
+        // The fills stop with the budget: none is left once the twelve constants exhaust it
+        let search = brain.search(&targets, &config.clone().with_max_evaluations(5));
+
+        assert!(search.exhausted);
+        assert_eq!(search.evaluations, 12);
+        assert_eq!(search.connections[0], Err(SearchError::BudgetExceeded));
+
+        // and a budget cutting the fills short still ranks those evaluated
+        let search = brain.search(&targets, &config.with_max_evaluations(30));
+
+        assert!(search.exhausted);
+        assert!(search.evaluations < 12 + 10 * 10);
+        assert!(search.connections[0].is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_observational_equivalence_task() -> Result<(), Box<dyn std::error::Error> > {
+        let task = data_source().load_task("training", "3c9b0459").await?;
+        let config = SearchConfig::new(2, 1e-6).with_strategy(Strategy::ObservationalEquivalence);
+        let solution = super::solver::solve(&task, &config);
+
+        assert_eq!(solution.cost, 0.0);
+        assert_eq!(Some(solution.predictions), task.test.into_iter().map(|(_, output)| output).collect());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_errors() {
+        let neg_neuron = Arc::new(Neuron::new(
+            "neg",
+            RwLock::new(Arc::new(|inputs: &[NeuronValue]| {
+                if inputs.len() != 1 {
+                    return None;
+                }
+
+                match &inputs[0] {
+                    NeuronValue::Int64(a) => Some(NeuronValue::Int64(-a)),
+                    _ => None,
+                }
+            })),
+            vec![ValueType::Int64],
+            ValueType::Int64,
+        ));
+
+        let none_neuron = Arc::new(Neuron::new(
+            "none",
+            RwLock::new(Arc::new(|_inputs: &[NeuronValue]| None)),
+            vec![],
+            ValueType::Bool,
+        ));
+
+        let brain: Brain = Brain::new(vec![neg_neuron, none_neuron]);
+        let connections = brain.learn(&[
+            NeuronValue::String("a".into()),
+            NeuronValue::Int64(-1),
+            NeuronValue::Bool(true),
+        ], 2, 1e-6);
+
+        assert_eq!(connections[0], Err(SearchError::NoCandidates(ValueType::String)));
+        assert_eq!(connections[1], Err(SearchError::NoParameters(ValueType::Int64)));
+        assert_eq!(connections[2], Err(SearchError::NoSolution(ValueType::Bool)));
+    }
+
+    #[tokio::test]
+    async fn test_evaluation() -> Result<(), Box<dyn std::error::Error> > {
+        let task = data_source().load_task("training", "3c9b0459").await?;
+        let (report, solution) = evaluate_task("3c9b0459", &task, &Budget::default());
+
+        assert!(report.solved);
+        assert_eq!(report.program, solution.program?.to_string());
+        assert!(report.nodes > 0);
+
+        let report = Report { tasks: vec![report] };
+        let csv = report.to_csv();
+
+        assert_eq!(report.solved(), 1);
+        assert!(csv.starts_with("task,solved,program,cost,level,time,nodes,error,failed_pairs\n3c9b0459,true,"));
+
+        let task = data_source().load_task("training", "0d3d703e").await?;
+        let budget = Budget {
+            max_level: 1,
+            sketches: vec![super::solver::parse_sketch("map(input, Map)")?],
+            ..Budget::default()
+        };
+        let (report, _) = evaluate_task("0d3d703e", &task, &budget);
+
+        assert!(report.solved);
+        assert_eq!(report.program, "map(input, infer_color_mapping(train_pairs))");
+
+        // A task failing to load is reported with its error, the other tasks still being evaluated
+        struct Missing(DirectorySource);
+
+        impl TaskSource for Missing {
+            async fn load_task(&self, folder: &str, task: &str) -> Result<Task, Box<dyn std::error::Error> > {
+                match task {
+                    "missing" => Err("No task missing".into()),
+                    _ => self.0.load_task(folder, task).await,
+                }
+            }
+
+            async fn task_ids(&self, _folder: &str) -> Result<Vec<String>, Box<dyn std::error::Error> > {
+                Ok(vec!["missing".into(), "3c9b0459".into()])
+            }
+        }
+
+        let mut loaded = Vec::new();
+        let report = evaluate(&Missing(data_source()), "training", &Budget::default(), |report, outcome| {
+            loaded.push((report.task.clone(), outcome.is_some()));
+        }).await?;
+
+        assert_eq!(loaded, [("missing".to_string(), false), ("3c9b0459".to_string(), true)]);
+        assert_eq!(report.tasks[0].error.as_deref(), Some("No task missing"));
+        assert!(!report.tasks[0].solved);
+        assert_eq!(report.solved(), 1);
+
+        // Sketches are parsed once for every task
+        assert_eq!(
+            super::solver::parse_sketch("map(input, Grids)").err(),
+            Some(ParseError::TypeMismatch { expected: ValueType::Map, found: ValueType::Grids })
+        );
+        assert_eq!(
+            super::solver::parse_sketch("fill_regions(input, region3, Int8)")?.holes(),
+            [ValueType::Int8]
+        );
+
+        // Sketches may use the generic neurons, instantiated at the types of their inputs
+        assert_eq!(
+            super::solver::parse_sketch("first(train_pairs)")?.neuron().output_type(),
+            &ValueType::Tuple(vec![ValueType::Grid, ValueType::Grid])
+        );
+
+        let budget = Budget {
+            max_level: 1,
+            sketches: vec![super::solver::parse_sketch("map(identity(input), Map)")?],
+            ..Budget::default()
+        };
+        let (report, _) = evaluate_task("0d3d703e", &task, &budget);
+
+        assert!(report.solved);
+        assert_eq!(report.program, "map(identity(input), infer_color_mapping(train_pairs))");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_task_sources() -> Result<(), Box<dyn std::error::Error> > {
+        // Task ids are sorted whatever the order of the directory entries
+        assert_eq!(data_source().task_ids("training").await?, ["0d3d703e", "253bf280", "3c9b0459"]);
+
+        let challenges = concat!(env!("CARGO_MANIFEST_DIR"), "/data/combined/challenges.json");
+        let solutions = concat!(env!("CARGO_MANIFEST_DIR"), "/data/combined/solutions.json");
+
+        // Without solutions the test outputs are unknown, and the folder is ignored
+        let source = CombinedSource::new(challenges, None)?;
+        let task = source.load_task("evaluation", "b2").await?;
+
+        assert_eq!(source.task_ids("evaluation").await?, ["a1", "b2"]);
+        assert_eq!(task.train, [(array![[1, 2]], array![[2, 1]])]);
+        assert_eq!(task.test, [(array![[3, 4]], None), (array![[5, 6], [7, 8]], None)]);
+        assert_eq!(source.load_task("evaluation", "c3").await.err().unwrap().to_string(), "Task c3 not found");
+
+        // The solutions fill in the test outputs of each task
+        let source = CombinedSource::new(challenges, Some(Path::new(solutions)))?;
+        let task = source.load_task("evaluation", "b2").await?;
+
+        assert_eq!(
+            task.test,
+            [(array![[3, 4]], Some(array![[4, 3]])), (array![[5, 6], [7, 8]], Some(array![[6, 5], [8, 7]]))]
+        );
+        assert_eq!(source.load_task("evaluation", "a1").await?.test, [(array![[5]], Some(array![[6]]))]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_values() {
+        let hash = |v: &NeuronValue| {
+            let mut hasher = DefaultHasher::new();
+
+            v.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        assert_eq!(NeuronValue::Int16(-300).value_type(), ValueType::Int16);
+        assert_eq!(NeuronValue::Int16(-300).heuristic(&NeuronValue::Int16(300)), 600.0);
+        assert_eq!(NeuronValue::UInt8(3).heuristic(&NeuronValue::UInt8(250)), 247.0);
+        assert_eq!(NeuronValue::UInt64(u64::MAX).heuristic(&NeuronValue::UInt64(0)), u64::MAX as f64);
+        assert_eq!(NeuronValue::Index(2).heuristic(&NeuronValue::Index(7)), 5.0);
+        assert_eq!(NeuronValue::Index(2).heuristic(&NeuronValue::UInt64(2)), f64::INFINITY);
+
+        assert_ne!(NeuronValue::Int8(1), NeuronValue::Int16(1));
+        assert_ne!(NeuronValue::UInt32(1), NeuronValue::Index(1));
+        assert_eq!(NeuronValue::UInt16(1), NeuronValue::UInt16(1));
+        assert_eq!(hash(&NeuronValue::Index(1)), hash(&NeuronValue::Index(1)));
+        assert_ne!(hash(&NeuronValue::Index(1)), hash(&NeuronValue::UInt64(1)));
+
+        let a: HashMap<i8, i8> = (0..10).map(|i| (i, 9 - i)).collect();
+        let b: HashMap<i8, i8> = (0..10).rev().map(|i| (i, 9 - i)).collect();
+
+        assert_eq!(hash(&NeuronValue::Map(a)), hash(&NeuronValue::Map(b)));
+
+        let shift_neuron = Neuron::from_fn("shift", |i: usize, n: u16| i.checked_add(n as usize));
+
+        assert_eq!(shift_neuron.input_types(), [ValueType::Index, ValueType::UInt16]);
+        assert_eq!(shift_neuron.apply(&[NeuronValue::Index(4), NeuronValue::UInt16(3)]), Some(NeuronValue::Index(7)));
+
+        let registry = NeuronRegistry::try_from(vec![Arc::new(shift_neuron)]).unwrap();
+        let connection = parse_connection("shift(4, 65535)", &registry).unwrap();
+
+        assert_eq!(connection.output(), Some(NeuronValue::Index(65539)));
+        assert_eq!(connection.to_string(), "shift(4, 65535)");
+        assert_eq!(
+            parse_connection("shift(4, 65536)", &registry),
+            Err(ParseError::InvalidLiteral { literal: "65536".into(), value_type: ValueType::UInt16 })
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(NeuronValue::Int16(-3).to_string(), "-3");
+        assert_eq!(NeuronValue::Double(0.5).to_string(), "0.5");
+        assert_eq!(NeuronValue::String("ab".into()).to_string(), "ab");
+        assert_eq!(NeuronValue::ValueType(ValueType::Grids).to_string(), "Grids");
+        assert_eq!(NeuronValue::Grid(array![[1, 2], [3, 0]]).to_string(), "12\n30");
+        assert_eq!(NeuronValue::Grid(array![[1, -2], [10, 0]]).to_string(), "1 -2\n10 0");
+        assert_eq!(NeuronValue::Grids(vec![array![[1]], array![[2, 3]]]).to_string(), "1\n\n23");
+        assert_eq!(NeuronValue::PairGrids(vec![(array![[1]], array![[2]])]).to_string(), "1\n→\n2");
+        assert_eq!(NeuronValue::Map(HashMap::from([(3, 4), (1, 2)])).to_string(), "{1→2, 3→4}");
+        assert_eq!(
+            NeuronValue::LocationPairs(vec![vec![((0, 0), (1, 2)), ((2, 2), (0, 1))], vec![]]).to_string(),
+            "[[(0, 0)-(1, 2), (2, 2)-(0, 1)], []]"
+        );
+        assert_eq!(
+            NeuronValue::RegionsList(vec![vec![vec![(0, 0), (0, 1)], vec![(2, 2)]]]).to_string(),
+            "[[[(0, 0), (0, 1)], [(2, 2)]]]"
+        );
+
+        let map_neuron = Arc::new(Neuron::from_fn("map", |a: Vec<Array2<i8> >, b: HashMap<i8, i8>| map(&a, &b)));
+        let connection = Connection::new(map_neuron, &[
+            ConnectionValue::Value(NeuronValue::ValueType(ValueType::Grids)),
+            ConnectionValue::Value(NeuronValue::Map(HashMap::from([(1, 2)]))),
+        ]);
+
+        assert_eq!(connection.to_string(), "map(Grids, {1→2})");
+    }
+
+    #[test]
+    fn test_heuristics() {
+        assert_eq!(super::airs::hungarian(&[vec![4.0, 1.0, 3.0], vec![2.0, 0.0, 5.0], vec![3.0, 2.0, 2.0]]), 5.0);
+        assert_eq!(super::airs::hungarian(&[]), 0.0);
+
+        assert_eq!(NeuronValue::Int8(-128).heuristic(&NeuronValue::Int8(127)), 255.0);
+        assert_eq!(NeuronValue::Int64(i64::MIN).heuristic(&NeuronValue::Int64(0)), 2f64.powi(63));
+        assert_eq!(NeuronValue::Index(2).heuristic(&NeuronValue::Index(5)), 3.0);
+        assert_eq!(NeuronValue::ValueType(ValueType::Grid).heuristic(&NeuronValue::ValueType(ValueType::Grid)), 0.0);
+        assert_eq!(NeuronValue::ValueType(ValueType::Grid).heuristic(&NeuronValue::ValueType(ValueType::Grids)), 1.0);
+        assert_eq!(NeuronValue::Int8(1).heuristic(&NeuronValue::Int16(1)), f64::INFINITY);
+
+        let map = NeuronValue::Map(HashMap::from([(1, 2), (3, 4)]));
+
+        assert_eq!(map.heuristic(&map), 0.0);
+        assert_eq!(map.heuristic(&NeuronValue::Map(HashMap::from([(1, 2), (3, 5)]))), 2.0);
+
+        let pairs = NeuronValue::PairGrids(vec![(array![[1, 2]], array![[3]])]);
+
+        assert_eq!(pairs.heuristic(&pairs), 0.0);
+        assert_eq!(pairs.heuristic(&NeuronValue::PairGrids(vec![(array![[1, 3]], array![[5]])])), 3.0);
+        assert_eq!(pairs.heuristic(&NeuronValue::PairGrids(vec![])), 101.0);
+
+        let locations = NeuronValue::LocationPairs(vec![vec![((0, 0), (0, 2)), ((3, 3), (5, 3))]]);
+
+        assert_eq!(locations.heuristic(&locations), 0.0);
+        // Matched regardless of order, each pair one cell off
+        assert_eq!(
+            locations.heuristic(&NeuronValue::LocationPairs(vec![vec![((3, 3), (5, 4)), ((0, 0), (1, 2))]])),
+            2.0
+        );
+        assert_eq!(
+            locations.heuristic(&NeuronValue::LocationPairs(vec![vec![((0, 0), (0, 2))]])),
+            100.0
+        );
+
+        // Each unmatched pair costs the same whether or not the pairs are too many to be matched optimally
+        let column = |n: isize| NeuronValue::LocationPairs(vec![(0..n).map(|i| ((i, 0), (i, 1))).collect()]);
+        let empty = NeuronValue::LocationPairs(vec![vec![]]);
+
+        assert_eq!(column(50).heuristic(&column(60)), 1000.0);
+        assert_eq!(column(90).heuristic(&column(100)), 1000.0);
+        assert_eq!(empty.heuristic(&column(10)), 1000.0);
+        assert_eq!(empty.heuristic(&column(100)), 10000.0);
+
+        let regions = NeuronValue::RegionsList(vec![vec![vec![(0, 0), (0, 1)], vec![(2, 2)]]]);
+
+        assert_eq!(regions.heuristic(&NeuronValue::RegionsList(vec![vec![vec![(2, 2)], vec![(0, 1), (0, 0)]]])), 0.0);
+        assert_eq!(regions.heuristic(&NeuronValue::RegionsList(vec![vec![vec![(2, 2)]]])), 1.0);
+    }
+
+    #[test]
+    fn test_grid_metrics() {
+        use super::airs::{CellMismatch, ColorDistance, ColorIoU, EditDistance, GridMetric, ObjectDistance};
+
+        let grid = array![[1, 0], [0, 1]];
+        let metrics: [&dyn GridMetric; 5] = [&ColorDistance, &CellMismatch, &ColorIoU, &EditDistance, &ObjectDistance::default()];
+
+        for metric in metrics {
+            assert_eq!(metric.distance(&grid, &grid), 0.0);
+            assert!(metric.distance(&grid, &array![[1, 0], [0, 2]]) > 0.0);
+        }
+
+        // A wrong color costs the same whatever the color, unlike with ColorDistance
+        assert_eq!(CellMismatch.distance(&grid, &array![[9, 0], [0, 1]]), 1.0);
+        assert_eq!(CellMismatch.distance(&grid, &array![[2, 0], [0, 1]]), 1.0);
+        assert_eq!(ColorDistance.distance(&grid, &array![[9, 0], [0, 1]]), 8.0);
+        assert_eq!(CellMismatch.distance(&grid, &array![[1, 0]]), 2.0);
+
+        // Color 1 overlaps on 1 of 2 cells, color 0 on 2 of 3 and color 2 on none
+        let iou = ColorIoU.distance(&grid, &array![[1, 0], [0, 0]]);
+        assert!((iou - (0.5 + 1.0 / 3.0)).abs() < 1e-9);
+
+        // A missing row costs its cells, a missing column one cell per row
+        assert_eq!(EditDistance.distance(&grid, &array![[1, 0]]), 2.0);
+        assert_eq!(EditDistance.distance(&grid, &array![[1], [0]]), 2.0);
+        assert_eq!(EditDistance.distance(&grid, &array![[1, 0], [0, 1], [1, 1]]), 2.0);
+
+        // Moving an object costs its displacement, recoloring it 1
+        let object = array![[0, 0, 0], [0, 3, 0], [0, 3, 0]];
+        assert_eq!(ObjectDistance::default().distance(&object, &array![[0, 0, 3], [0, 0, 3], [0, 0, 0]]), 2.0);
+        assert_eq!(ObjectDistance::default().distance(&object, &array![[0, 0, 0], [0, 4, 0], [0, 4, 0]]), 1.0);
+        assert_eq!(ObjectDistance::default().distance(&object, &array![[0, 0, 0], [0, 0, 0], [0, 0, 0]]), 3.0);
+
+        let target = NeuronValue::Grids(vec![array![[9, 1]]]);
+        let value = NeuronValue::Grids(vec![array![[1, 1]]]);
+        assert_eq!(value.heuristic(&target), 8.0);
+        assert_eq!(value.distance(&target, &CellMismatch), 1.0);
+        assert_eq!(SearchConfig::new(1, 1e-6).with_grid_metric(Arc::new(CellMismatch)).heuristic(&value, &target), 1.0);
+    }
+
+    #[test]
+    fn test_composite_types() {
+        let list_of = |t: ValueType| ValueType::List(Box::new(t));
+        let pair = ValueType::Tuple(vec![ValueType::Grid, ValueType::Grid]);
+
+        assert_eq!(list_of(ValueType::Grid).normalized(), ValueType::Grids);
+        assert_eq!(list_of(pair.clone()).normalized(), ValueType::PairGrids);
+        assert_eq!(Neuron::variable("grids", list_of(ValueType::Grid)).output_type(), &ValueType::Grids);
+        assert_eq!(ValueType::PairGrids.item_type(), Some(pair));
+        assert_eq!(list_of(list_of(ValueType::Grid)).normalized().to_string(), "List(Grids)");
+
+        let grids = vec![array![[1, 0], [0, 0]], array![[2, 2]]];
+        let sizes = Neuron::from_fn("sizes", |a: Vec<Array2<i8> >| a.iter().map(|g| g.len()).collect::<Vec<usize> >());
+        let first = Neuron::from_fn("first", |a: Vec<usize>| a.first().copied());
+        let zip = Neuron::from_fn("zip", |a: Vec<Array2<i8> >, b: Vec<usize>| a.into_iter().zip(b).collect::<Vec<_> >());
+
+        assert_eq!(sizes.input_types(), [ValueType::Grids]);
+        assert_eq!(sizes.output_type(), &list_of(ValueType::Index));
+        assert_eq!(zip.output_type(), &list_of(ValueType::Tuple(vec![ValueType::Grid, ValueType::Index])));
+
+        let sizes_value = sizes.apply(&[NeuronValue::Grids(grids.clone())]).unwrap();
+
+        assert_eq!(sizes_value, NeuronValue::List(ValueType::Index, vec![NeuronValue::Index(4), NeuronValue::Index(2)]));
+        assert_eq!(sizes_value.to_string(), "[4, 2]");
+        assert_eq!(first.apply(std::slice::from_ref(&sizes_value)), Some(NeuronValue::Index(4)));
+        assert_eq!(first.apply(&[NeuronValue::List(ValueType::Index, vec![])]), None);
+        assert_eq!(
+            zip.apply(&[NeuronValue::Grids(grids.clone()), sizes_value.clone()]).unwrap().to_string(),
+            "[(10\n00, 4), (22, 2)]"
+        );
+
+        // Lists of grid pairs built from tuples are PairGrids
+        let pairs = Neuron::from_fn("pairs", |a: Vec<Array2<i8> >| a.iter().map(|g| (g.clone(), g.clone())).collect::<Vec<_> >());
+
+        assert_eq!(pairs.output_type(), &ValueType::PairGrids);
+        assert_eq!(pairs.apply(&[NeuronValue::Grids(grids.clone())]).unwrap().value_type(), ValueType::PairGrids);
+
+        let none = NeuronValue::Option(ValueType::Index, None);
+        let some = NeuronValue::Option(ValueType::Index, Some(Box::new(NeuronValue::Index(3))));
+
+        assert_eq!(some.to_string(), "Some(3)");
+        assert_eq!(some.value_type(), ValueType::Option(Box::new(ValueType::Index)));
+        assert_eq!(some.heuristic(&none), 100.0);
+        assert_eq!(
+            sizes_value.heuristic(&NeuronValue::List(ValueType::Index, vec![NeuronValue::Index(4), NeuronValue::Index(5)])),
+            3.0
+        );
+
+        let registry = NeuronRegistry::try_from(vec![Arc::new(sizes), Arc::new(first)]).unwrap();
+        let sketch = parse_connection("first(sizes(List(Grid)))", &registry).unwrap();
+
+        assert_eq!(sketch.holes(), [ValueType::Grids]);
+        assert_eq!(sketch.to_string(), "first(sizes(Grids))");
+        assert!(matches!(
+            parse_connection("first(List(Index))", &registry).unwrap().holes()[..],
+            [ValueType::List(_)]
+        ));
+        assert_eq!(
+            parse_connection("first(List(Int8))", &registry).unwrap_err(),
+            ParseError::TypeMismatch { expected: list_of(ValueType::Index), found: list_of(ValueType::Int8) }
+        );
+        assert_eq!(
+            parse_connection("first(List(Foo))", &registry).unwrap_err(),
+            ParseError::UnknownType("Foo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_polymorphic_neurons() {
+        use super::airs::TypeBindings;
+
+        let list_of = |t: ValueType| ValueType::List(Box::new(t));
+        let mut bindings = TypeBindings::new();
+
+        assert!(list_of(ValueType::var("T")).unify(&ValueType::Grids, &mut bindings));
+        assert_eq!(bindings["T"], ValueType::Grid);
+        assert!(!ValueType::var("T").unify(&ValueType::Int8, &mut bindings));
+        assert!(!ValueType::var("T").unify(&list_of(ValueType::var("T")), &mut TypeBindings::new()));
+        assert_eq!(list_of(ValueType::var("T")).substitute(&bindings), ValueType::Grids);
+
+        let pair = ValueType::Tuple(vec![ValueType::var("A"), ValueType::var("A")]);
+        let mut bindings = TypeBindings::new();
+
+        assert!(list_of(pair.clone()).unify(&ValueType::PairGrids, &mut bindings));
+        assert_eq!(bindings["A"], ValueType::Grid);
+        assert!(!pair.unify(&ValueType::Tuple(vec![ValueType::Grid, ValueType::Int8]), &mut TypeBindings::new()));
+
+        let input = vec![array![[1, 2], [3, 4]], array![[5, 6]]];
+        let mut registry = NeuronRegistry::try_from(library::generic_neurons()).unwrap();
+
+        let grids = input.clone();
+
+        registry.register(Arc::new(Neuron::from_fn("input", move || grids.clone()))).unwrap();
+        registry.register(library::map_list(library::example_registry().get("fliplr").unwrap())).unwrap();
+
+        let program = parse_connection("first(zip(input, map_fliplr(input)))", &registry).unwrap();
+
+        assert_eq!(program.neuron().output_type(), &ValueType::Tuple(vec![ValueType::Grid, ValueType::Grid]));
+        assert_eq!(program.to_string(), "first(zip(input, map_fliplr(input)))");
+        assert_eq!(
+            program.output(),
+            Some(NeuronValue::Tuple(vec![NeuronValue::Grid(input[0].clone()), NeuronValue::Grid(array![[2, 1], [4, 3]])]))
+        );
+        assert!(matches!(
+            parse_connection("zip(input, first(input))", &registry),
+            Err(ParseError::TypeMismatch { .. })
+        ));
+
+        // `map_first` maps `first` over lists of lists of any item type
+        let groups = NeuronValue::list(
+            ValueType::Grids,
+            vec![NeuronValue::Grids(input.clone()), NeuronValue::Grids(vec![array![[7]]])],
+        );
+
+        registry.register(Arc::new(Neuron::variable("groups", groups.value_type()))).unwrap();
+
+        let program = parse_connection("map_first(groups)", &registry).unwrap();
+
+        assert_eq!(program.neuron().output_type(), &ValueType::Grids);
+        assert_eq!(
+            program.output_in(&Context::new().with("groups", groups)),
+            Some(NeuronValue::Grids(vec![input[0].clone(), array![[7]]]))
+        );
+
+        // `first` is instantiated at List(Grid) = Grids, the output type of `input` and `map_fliplr`
+        let brain = Brain::from_registry(&registry);
+        let target = NeuronValue::Grid(array![[6, 5]]);
+
+        for strategy in [Strategy::Exhaustive, Strategy::ObservationalEquivalence, Strategy::BestFirst] {
+            let search = brain.search(std::slice::from_ref(&target), &SearchConfig::new(2, 1e-6).with_strategy(strategy));
+            let program = search.connections[0].as_ref().unwrap();
+
+            assert_eq!(program.to_string(), "last(map_fliplr(input))", "{:?}", strategy);
+            assert_eq!(search.ranked[0][0].cost, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_context() {
+        let mut registry = library::example_registry();
+
+        registry.register(Arc::new(Neuron::variable("input", ValueType::Grid))).unwrap();
+
+        let program = parse_connection("fliplr(input)", &registry).unwrap();
+        let examples: Vec<Array2<i8> > = (0..8).map(|i| array![[i, 0, 1]]).collect();
+
+        // The same program is evaluated on every example at once, its neurons being left untouched
+        let outputs: Vec<Option<NeuronValue> > = std::thread::scope(|scope| {
+            let handles: Vec<_> = examples
+                .iter()
+                .map(|example| {
+                    let program = &program;
+                    let context = Context::new().with("input", NeuronValue::Grid(example.clone()));
+
+                    scope.spawn(move || program.output_in(&context))
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        for (example, output) in examples.iter().zip(outputs) {
+            assert_eq!(output, Some(NeuronValue::Grid(super::airs::fliplr(example))));
+        }
+
+        // Variables are only bound by a context
+        let context = Context::new().with("input", NeuronValue::Grid(array![[1, 2]]));
+
+        assert_eq!(program.output(), None);
+        assert_eq!(program.output_in(&context), Some(NeuronValue::Grid(array![[2, 1]])));
+
+        // Only constant neurons are bound
+        let bound = context.clone().with("fliplr", NeuronValue::Grid(array![[0]]));
+        assert_eq!(program.output_in(&bound), program.output_in(&context));
+
+        let context = super::solver::context(&[array![[0, 3], [3, 3]]]);
+        assert_eq!(context.get("input"), Some(&NeuronValue::Grids(vec![array![[0, 3], [3, 3]]])));
+        assert_eq!(context.get("region3"), Some(&NeuronValue::RegionsList(vec![vec![vec![(0, 1), (1, 0), (1, 1)]]])));
+        assert_eq!(context.get("region5"), Some(&NeuronValue::RegionsList(vec![vec![]])));
+    }
+
+    #[test]
+    fn test_examples() {
+        use super::airs::Example;
+
+        let mut registry = NeuronRegistry::try_from(library::constant_neurons()).unwrap();
+
+        registry.register_all([
+            Arc::new(Neuron::variable("input", ValueType::Grid)),
+            Arc::new(Neuron::from_fn("fliplr", |g: Array2<i8>| super::airs::fliplr(&g))),
+            Arc::new(Neuron::from_fn("flipud", |g: Array2<i8>| super::airs::flipud(&g))),
+            Arc::new(Neuron::from_fn("rot90", |g: Array2<i8>| super::airs::rot90(&g))),
+            Arc::new(Neuron::from_fn("replace", |g: Array2<i8>, from: i8, to: i8| {
+                g.mapv(|v| if v == from { to } else { v })
+            })),
+        ]).unwrap();
+
+        let transform = |g: &Array2<i8>| super::airs::rot90(&super::airs::fliplr(g));
+        let examples: Vec<Example> = [array![[1, 2, 3]], array![[1, 0], [0, 2]], array![[4], [5]]]
+            .into_iter()
+            .map(|g| Example::new(Context::new().with("input", NeuronValue::Grid(g.clone())), NeuronValue::Grid(transform(&g))))
+            .collect();
+        let test = array![[1, 2], [3, 4], [5, 6]];
+        let test_context = Context::new().with("input", NeuronValue::Grid(test.clone()));
+        let brain = Brain::from_registry(&registry);
+
+        for strategy in [Strategy::Exhaustive, Strategy::ObservationalEquivalence, Strategy::BestFirst] {
+            let search = brain.search_examples(&examples, &SearchConfig::new(2, 1e-6).with_strategy(strategy));
+            let program = search.connections[0].as_ref().unwrap();
+
+            assert_eq!(search.ranked[0][0].cost, 0.0, "{:?}", strategy);
+            assert_eq!(program.output_in(&test_context), Some(NeuronValue::Grid(transform(&test))), "{:?}", strategy);
+
+            for example in &examples {
+                assert_eq!(program.output_in(&example.context).as_ref(), Some(&example.output));
+            }
+        }
+
+        // The input is only bound by the examples
+        let program = brain.search_examples(&examples, &SearchConfig::new(2, 1e-6)).connections.remove(0).unwrap();
+        assert_eq!(program.output(), None);
+
+        // A neuron wrong on one example only is not a solution
+        let mut wrong = examples.clone();
+        wrong[1].output = NeuronValue::Grid(array![[9]]);
+        assert!(brain.search_examples(&wrong, &SearchConfig::new(1, 1e-6)).ranked[0][0].cost > 0.0);
+
+        // Sketch literals are the same on every example
+        let examples: Vec<Example> = [array![[1, 2]], array![[2, 1], [1, 1]]]
+            .into_iter()
+            .map(|g| {
+                let output = super::airs::fliplr(&g).mapv(|v| if v == 1 { 5 } else { v });
+
+                Example::new(Context::new().with("input", NeuronValue::Grid(g)), NeuronValue::Grid(output))
+            })
+            .collect();
+        let sketch = parse_connection("replace(Grid, 1, Int8)", &registry).unwrap();
+        let search = brain.search_examples(&examples, &SearchConfig::new(1, 1e-6).with_sketch(sketch));
+        let program = search.connections[0].as_ref().unwrap();
+
+        assert_eq!(program.to_string(), "replace(fliplr(input), 1, 5)");
+        assert_eq!(search.ranked[0][0].cost, 0.0);
+    }
+
+    #[test]
+    fn test_output_cache() {
+        use super::airs::{Example, OutputCache};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&calls);
+        let input = Arc::new(Neuron::variable("input", ValueType::Grid));
+        let mut registry = library::example_registry();
+
+        registry.register(Arc::clone(&input)).unwrap();
+        registry.register(Arc::new(Neuron::from_fn("count", move |g: Array2<i8>| {
+            counted.fetch_add(1, Ordering::Relaxed);
+            g
+        }))).unwrap();
+
+        let program = parse_connection("fliplr(flipud(count(input)))", &registry).unwrap();
+        let cache = OutputCache::new(1 << 20);
+        let context = Context::new().with("input", NeuronValue::Grid(array![[1, 2], [3, 4]]));
+
+        assert_eq!(program.output_cached(&context, &cache), program.output_in(&context));
+        assert_eq!(program.output_cached(&context, &cache), program.output_in(&context));
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 3));
+        assert_eq!(stats.hit_rate(), 1.0 / 4.0);
+
+        // Entries are specific to the context of the evaluation
+        let other = Context::new().with("input", NeuronValue::Grid(array![[5, 6]]));
+        assert_eq!(program.output_cached(&other, &cache), program.output_in(&other));
+        assert_eq!(cache.stats().misses, 6);
+
+        // Structurally equal connections share their entries
+        let copy = parse_connection("fliplr(flipud(count(input)))", &registry).unwrap();
+        copy.output_cached(&context, &cache);
+        assert_eq!(cache.stats().hits, 2);
+
+        // The oldest entries are evicted beyond the memory cap
+        let small = OutputCache::new(cache.stats().bytes / 4);
+        program.output_cached(&context, &small);
+        program.output_cached(&other, &small);
+
+        let stats = small.stats();
+        assert!(stats.evictions > 0);
+        assert!(stats.bytes <= cache.stats().bytes / 4);
+
+        small.clear();
+        assert_eq!((small.stats().entries, small.stats().bytes), (0, 0));
+
+        // A search gives the same result with or without a cache
+        let grid = array![[1, 0], [0, 2], [3, 0]];
+        let context = Context::new().with("input", NeuronValue::Grid(grid.clone()));
+        // No program reaches the target, so that every connection is evaluated whatever the order
+        let target = [Example::new(context.clone(), NeuronValue::Grid(array![[9]]))];
+        let flipped = [Example::new(context, NeuronValue::Grid(super::airs::fliplr(&super::airs::flipud(&grid))))];
+        let mut registry = library::example_registry();
+
+        registry.register(input).unwrap();
+
+        let brain = Brain::from_registry(&registry);
+
+        for strategy in [Strategy::Exhaustive, Strategy::ObservationalEquivalence, Strategy::BestFirst] {
+            let config = SearchConfig::new(1, 1e-6).with_strategy(strategy);
+            let cache = Arc::new(OutputCache::new(1 << 24));
+            let expected = brain.search_examples(&target, &config);
+            let config = config.with_cache(Arc::clone(&cache));
+            let search = brain.search_examples(&target, &config);
+
+            assert_eq!(search.ranked[0][0].cost, expected.ranked[0][0].cost, "{:?}", strategy);
+            assert!(cache.stats().misses > 0, "{:?}", strategy);
+
+            // The sub-connections shared by the templates of the exhaustive search are evaluated once
+            if strategy == Strategy::Exhaustive {
+                assert!(cache.stats().hits > 0);
+            }
+
+        }
+
+        // A search over the same neurons finds every evaluation in the cache; the neurons of a search on
+        // examples are lifted anew by each search, so the input is a constant here
+        let mut registry = library::example_registry();
+
+        registry.register(Arc::new(Neuron::from_fn("input", move || grid.clone()))).unwrap();
+
+        let constant_brain = Brain::from_registry(&registry);
+        let target = NeuronValue::Grid(array![[9]]);
+
+        for strategy in [Strategy::Exhaustive, Strategy::ObservationalEquivalence, Strategy::BestFirst] {
+            let config = SearchConfig::new(1, 1e-6).with_strategy(strategy);
+            let cache = Arc::new(OutputCache::new(1 << 24));
+            let config = config.with_cache(Arc::clone(&cache));
+
+            let expected = constant_brain.search(std::slice::from_ref(&target), &config);
+
+            let misses = cache.stats().misses;
+            let search = constant_brain.search(std::slice::from_ref(&target), &config);
+
+            assert!(cache.stats().hits > 0, "{:?}", strategy);
+            assert_eq!(cache.stats().misses, misses, "{:?}", strategy);
+            assert_eq!(search.ranked[0][0].cost, expected.ranked[0][0].cost, "{:?}", strategy);
+            assert_eq!(search.evaluations, expected.evaluations, "{:?}", strategy);
+        }
+
+        // Sketch holes are filled with the connections of the bank, whose outputs are cached
+        let cache = Arc::new(OutputCache::new(1 << 24));
+        let sketch = parse_connection("fliplr(Grid)", &library::example_registry()).unwrap();
+        let config = SearchConfig::new(2, 1e-6).with_sketch(sketch).with_cache(Arc::clone(&cache));
+        let search = brain.search_examples(&flipped, &config);
+
+        assert_eq!(search.ranked[0][0].cost, 0.0);
+        assert!(cache.stats().hits > 0);
+    }
+
+    #[test]
+    fn test_search_stats() {
+        use super::airs::{Example, SearchObserver, SearchStats};
+        use std::sync::Mutex;
+
+        #[derive(Debug, Default)]
+        struct Recorder {
+            levels: Mutex<Vec<usize> >,
+            best: Mutex<Vec<f64> >,
+            finished: Mutex<Vec<SearchStats> >,
+        }
+
+        impl SearchObserver for Recorder {
+            fn on_level(&self, level: usize, _stats: &SearchStats) {
+                self.levels.lock().unwrap().push(level);
+            }
+
+            fn on_best(&self, stats: &SearchStats) {
+                self.best.lock().unwrap().push(stats.best_cost);
+            }
+
+            fn on_finish(&self, stats: &SearchStats) {
+                self.finished.lock().unwrap().push(stats.clone());
+            }
+        }
+
+        let grid = array![[1, 2], [3, 4]];
+        let output = super::airs::fliplr(&super::airs::flipud(&grid));
+        let target = [Example::new(Context::new().with("input", NeuronValue::Grid(grid)), NeuronValue::Grid(output))];
+        let mut registry = library::example_registry();
+
+        registry.register(Arc::new(Neuron::variable("input", ValueType::Grid))).unwrap();
+
+        let brain = Brain::from_registry(&registry);
+
+        for strategy in [Strategy::Exhaustive, Strategy::ObservationalEquivalence, Strategy::BestFirst] {
+            let recorder = Arc::new(Recorder::default());
+            let config = SearchConfig::new(2, 1e-6)
+                .with_strategy(strategy)
+                .with_observer(recorder.clone());
+            let search = brain.search_examples(&target, &config);
+            let stats = &search.stats;
+
+            assert_eq!(stats.evaluations, search.evaluations, "{:?}", strategy);
+            assert_eq!(stats.best_cost, 0.0, "{:?}", strategy);
+            assert_eq!(stats.best_program, Some(search.connections[0].as_ref().unwrap().to_string()), "{:?}", strategy);
+            assert!(stats.connections_per_level.len() > 1, "{:?}", strategy);
+            assert!(stats.connections_per_level[0] > 0, "{:?}", strategy);
+            assert!(stats.evaluations_per_second() > 0.0, "{:?}", strategy);
+            assert_eq!(stats.cache, None);
+
+            // Better candidates are reported with decreasing costs, the statistics once at the end
+            let best = recorder.best.lock().unwrap();
+            assert!(best.windows(2).all(|w| w[1] < w[0]), "{:?}", strategy);
+            assert_eq!(best.last(), Some(&0.0), "{:?}", strategy);
+            assert_eq!(*recorder.finished.lock().unwrap(), vec![stats.clone()], "{:?}", strategy);
+
+            let levels = recorder.levels.lock().unwrap();
+            match strategy {
+                Strategy::BestFirst => assert!(levels.is_empty()),
+                _ => assert_eq!(*levels, vec![0, 1, 2], "{:?}", strategy),
+            }
+
+            // The connections of level 0 are the neurons without inputs
+            if strategy == Strategy::Exhaustive {
+                let leaves = registry.neurons().iter().filter(|n| n.input_types().is_empty()).count();
+
+                assert_eq!(stats.connections_per_level[0], leaves);
+            }
+        }
+
+        // Cache statistics are included when the search has a cache, the best candidate without an observer
+        let cache = Arc::new(super::airs::OutputCache::new(1 << 20));
+        let config = SearchConfig::new(2, 1e-6).with_cache(cache.clone());
+        let search = brain.search_examples(&target, &config);
+
+        assert_eq!(search.stats.cache, Some(cache.stats()));
+        assert_eq!(search.stats.best_cost, 0.0);
+        assert_eq!(search.stats.best_program, Some(search.connections[0].as_ref().unwrap().to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_leave_one_out() -> Result<(), Box<dyn std::error::Error> > {
+        use super::airs::Example;
+
+        let constant = |name: &str, value: i64| {
+            Arc::new(Neuron::new(
+                name,
+                RwLock::new(Arc::new(move |_inputs: &[NeuronValue]| Some(NeuronValue::Int64(value)))),
+                vec![],
+                ValueType::Int64,
+            ))
+        };
+        let brain = Brain::new(vec![
+            constant("input", 2),
+            constant("four", 4),
+            Arc::new(Neuron::from_fn("double", |x: i64| 2 * x)),
+        ]);
+        let target = NeuronValue::Int64(4);
+        let folds = vec![
+            Example::new(Context::new().with("input", NeuronValue::Int64(3)), NeuronValue::Int64(6)),
+            Example::new(Context::new().with("input", NeuronValue::Int64(5)), NeuronValue::Int64(9)),
+        ];
+
+        // The constant is the cheapest program solving the target, but it fails every held out pair
+        let config = SearchConfig::new(2, 1e-6).with_top_k(2);
+        let search = brain.search(std::slice::from_ref(&target), &config);
+        assert_eq!(search.connections[0].as_ref().unwrap().to_string(), "four");
+        assert!(search.ranked[0].iter().all(|pair| pair.failures.is_empty()));
+
+        let search = brain.search(std::slice::from_ref(&target), &config.with_leave_one_out(folds));
+        let failures: Vec<(String, Vec<usize>)> = search.ranked[0]
+            .iter()
+            .map(|pair| (pair.connection.to_string(), pair.failures.clone()))
+            .collect();
+
+        assert_eq!(search.connections[0].as_ref().unwrap().to_string(), "double(input)");
+        assert_eq!(failures, vec![("double(input)".to_string(), vec![1]), ("four".to_string(), vec![0, 1])]);
+
+        // Each fold holds out one train pair of a task
+        let task = data_source().load_task("training", "0d3d703e").await?;
+        let folds = super::solver::leave_one_out(&task);
+
+        assert_eq!(folds.len(), task.train.len());
+
+        for (fold, (input, output)) in folds.iter().zip(&task.train) {
+            assert_eq!(fold.context.get("input"), Some(&NeuronValue::Grid(input.clone())));
+            assert_eq!(fold.output, NeuronValue::Grid(output.clone()));
+
+            match fold.context.get("train_pairs") {
+                Some(NeuronValue::PairGrids(pairs)) => assert_eq!(pairs.len(), task.train.len() - 1),
+                value => panic!("Unexpected train pairs {:?}", value),
+            }
+        }
+
+        let budget = Budget { leave_one_out: true, ..Budget::default() };
+        let (report, _) = evaluate_task("0d3d703e", &task, &budget);
+
+        // The color mapping inferred without a pair misses the colors only this pair uses
+        assert!(report.solved);
+        assert_eq!(report.failed_pairs, vec![0, 2, 3]);
+        assert!(Report { tasks: vec![report] }.to_csv().ends_with(",0 2 3\n"));
+
+        // The color mapping is the cheapest program solving the train pairs, but it misses the color only one
+        // pair maps; with the default options, validation keeps enough programs to prefer the segments
+        let task = Task {
+            train: vec![
+                (array![[1, 0, 1]], array![[1, 1, 1]]),
+                (array![[1, 1]], array![[1, 1]]),
+                (array![[2, 1, 1]], array![[2, 1, 1]]),
+            ],
+            test: vec![(array![[0, 1, 0, 1]], Some(array![[0, 1, 1, 1]]))],
+        };
+        let (report, _) = evaluate_task("segments", &task, &parse_options(&[])?.budget);
+
+        assert!(!report.solved);
+        assert_eq!(report.program, "map(input, infer_color_mapping(train_pairs))");
+
+        let (report, _) = evaluate_task("segments", &task, &parse_options(&args(&["--validation", "leave-one-out"]))?.budget);
+
+        assert!(report.solved);
+        assert!(report.program.starts_with("segments(input, region_pairs(region1), 1,"), "{}", report.program);
+        assert!(report.failed_pairs.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_neuron_from_fn() {
+        let map_neuron = Neuron::from_fn("map", |a: Vec<Array2<i8> >, b: HashMap<i8, i8>| map(&a, &b));
+
+        assert_eq!(map_neuron.input_types(), [ValueType::Grids, ValueType::Map]);
+        assert_eq!(map_neuron.output_type(), &ValueType::Grids);
+        assert_eq!(
+            map_neuron.apply(&[NeuronValue::Grids(vec![array![[1, 2]]]), NeuronValue::Map(HashMap::from([(1, 3)]))]),
+            Some(NeuronValue::Grids(vec![array![[3, 2]]]))
+        );
+        assert_eq!(map_neuron.apply(&[NeuronValue::Grids(vec![array![[1, 2]]]), NeuronValue::Int8(1)]), None);
+        assert_eq!(map_neuron.apply(&[NeuronValue::Grids(vec![array![[1, 2]]])]), None);
+
+        let div_neuron = Neuron::from_fn("div", |a: i64, b: i64| a.checked_div(b));
+
+        assert_eq!(div_neuron.input_types(), [ValueType::Int64, ValueType::Int64]);
+        assert_eq!(div_neuron.output_type(), &ValueType::Int64);
+        assert_eq!(div_neuron.apply(&[NeuronValue::Int64(7), NeuronValue::Int64(2)]), Some(NeuronValue::Int64(3)));
+        assert_eq!(div_neuron.apply(&[NeuronValue::Int64(7), NeuronValue::Int64(0)]), None);
+
+        let true_neuron = Neuron::from_fn("true", || true);
+
+        assert!(true_neuron.input_types().is_empty());
+        assert_eq!(true_neuron.output_type(), &ValueType::Bool);
+        assert_eq!(true_neuron.apply(&[]), Some(NeuronValue::Bool(true)));
+    }
+
+    #[test]
+    fn test_library() {
+        use super::airs::Example;
+
+        let grid = array![[1, 2, 0], [0, 3, 0]];
+
+        assert_eq!(rotate(&grid, 1), array![[0, 0], [2, 3], [1, 0]]);
+        assert_eq!(rotate(&grid, 4), grid);
+        assert_eq!(super::airs::transpose(&grid), array![[1, 0], [2, 3], [0, 0]]);
+        assert_eq!(super::airs::crop(&array![[0, 0, 0], [0, 5, 0], [0, 0, 6]], 0), array![[5, 0], [0, 6]]);
+        assert_eq!(super::airs::tile(&array![[1, 2]], 2, 2), array![[1, 2, 1, 2], [1, 2, 1, 2]]);
+        assert_eq!(super::airs::scale(&array![[1, 2]], 2), array![[1, 1, 2, 2], [1, 1, 2, 2]]);
+        assert_eq!(gravity(&grid, 0), array![[0, 2, 0], [1, 3, 0]]);
+        assert_eq!(gravity(&grid, 1), array![[0, 1, 2], [0, 0, 3]]);
+        assert_eq!(gravity(&grid, 2), array![[1, 2, 0], [0, 3, 0]]);
+        assert_eq!(gravity(&grid, 3), array![[1, 2, 0], [3, 0, 0]]);
+        assert_eq!(complete_symmetry(&array![[1, 2, 0], [0, 3, 4]], true), array![[1, 2, 1], [4, 3, 4]]);
+        assert_eq!(complete_symmetry(&array![[1, 0], [0, 3]], false), array![[1, 3], [1, 3]]);
+        assert_eq!(replace_color(&grid, 0, 7), array![[1, 2, 7], [7, 3, 7]]);
+        assert_eq!(fill_regions(&grid, &[vec![(0, 0), (1, 1)]], 9), array![[9, 2, 0], [0, 9, 0]]);
+        assert_eq!(segments(&array![[1, 0, 1]], &[((0, 0), (0, 2))], 5, true, false), array![[1, 5, 5]]);
+
+        let registry = library::example_registry();
+
+        for neuron in registry.neurons() {
+            let inputs: Vec<NeuronValue> = neuron
+                .input_types()
+                .iter()
+                .map(|t| match t {
+                    ValueType::Grid => NeuronValue::Grid(grid.clone()),
+                    ValueType::Int8 => NeuronValue::Int8(2),
+                    ValueType::Bool => NeuronValue::Bool(true),
+                    ValueType::Map => NeuronValue::Map(HashMap::from([(1, 2)])),
+                    ValueType::PairGrids => NeuronValue::PairGrids(vec![(grid.clone(), grid.clone())]),
+                    ValueType::LocationPairs => NeuronValue::LocationPairs(vec![vec![((0, 0), (1, 2))]]),
+                    ValueType::RegionsList => NeuronValue::RegionsList(vec![vec![vec![(0, 0)]]]),
+                    other => panic!("No library neuron takes {:?}", other),
+                })
+                .collect();
+
+            let output = neuron.apply(&inputs);
+
+            assert_eq!(output.as_ref().map(|v| v.value_type()), Some(neuron.output_type().clone()), "{}", neuron.name());
+
+            // The values the library produces can be written in programs
+            assert_literal_round_trip(&output.unwrap());
+        }
+
+        let input = [grid.clone(), array![[4, 0], [0, 5]]];
+
+        // A program on single grids is searched on each example
+        let examples: Vec<Example> = input
+            .iter()
+            .map(|g| {
+                let output = rotate(&gravity(g, 3), 1);
+
+                Example::new(Context::new().with("input", NeuronValue::Grid(g.clone())), NeuronValue::Grid(output))
+            })
+            .collect();
+        let mut neurons = registry.neurons().to_vec();
+
+        neurons.push(Arc::new(Neuron::variable("input", ValueType::Grid)));
+
+        let search = Brain::new(neurons).search_examples(&examples, &SearchConfig::new(2, 1e-6));
+
+        assert_eq!(search.ranked[0][0].cost, 0.0);
+        assert_eq!(search.connections[0].as_ref().unwrap().depth(0), 2);
+    }
+
     #[tokio::test]
     async fn test_task3c9b0459() -> Result<(), Box<dyn std::error::Error> > {
-        let task = load_task("training", "3c9b0459").await?;
+        let task = data_source().load_task("training", "3c9b0459").await?;
         let train_pairs = input_output_pairs(&task.train);
         let test_pairs = input_option_output_pairs(&task.test);
         
@@ -382,7 +2007,7 @@ mod tests
             ValueType::Grids,
         ));
 
-        let mut input = train_pairs.inputs;
+        let input = train_pairs.inputs;
 
         let input_neuron = Arc::new(Neuron::new(
             "input",
@@ -402,7 +2027,7 @@ mod tests
         let target = NeuronValue::Grids(train_pairs.outputs);
 
         let brain: Brain = Brain::new(neurons);
-        let connections = brain.learn(&[target.clone()].to_vec(), 2, 1e-6);
+        let connections = brain.learn(&[target.clone()].to_vec(), 2, 1e-6).into_iter().collect::<Result<Vec<_>, _> >()?;
 
         assert_ne!(connections.len(), 0);
 
@@ -410,23 +2035,16 @@ mod tests
 
         assert!(connections[0].output().unwrap().heuristic(&target) == 0.0);
         
-        input = test_pairs.inputs;
-        
-        {
-            let mut func = input_neuron.function.write().unwrap();
-            *func = Arc::new(move |_inputs: &[NeuronValue]| {
-                Some(NeuronValue::Grids(input.clone()))
-            });
-        }
-        
-        assert!(connections[0].output().unwrap().heuristic(&NeuronValue::Grids(test_pairs.outputs)) == 0.0);
+        let context = Context::new().with("input", NeuronValue::Grids(test_pairs.inputs));
+
+        assert!(connections[0].output_in(&context).unwrap().heuristic(&NeuronValue::Grids(test_pairs.outputs)) == 0.0);
         
         Ok(())
     }
     
     #[tokio::test]
     async fn test_task0d3d703e() -> Result<(), Box<dyn std::error::Error> > {
-        let task = load_task("training", "0d3d703e").await?;
+        let task = data_source().load_task("training", "0d3d703e").await?;
         let train_pairs = input_output_pairs(&task.train);
         let test_pairs = input_option_output_pairs(&task.test);
 
@@ -507,7 +2125,7 @@ mod tests
             ValueType::PairGrids,
         ));
 
-        let mut input = train_pairs.inputs;
+        let input = train_pairs.inputs;
 
         let input_neuron = Arc::new(Neuron::new(
             "input",
@@ -529,7 +2147,7 @@ mod tests
         let target = NeuronValue::Grids(train_pairs.outputs);
 
         let brain: Brain = Brain::new(neurons);
-        let connections = brain.learn(&[target.clone()].to_vec(), 2, 1e-6);
+        let connections = brain.learn(&[target.clone()].to_vec(), 2, 1e-6).into_iter().collect::<Result<Vec<_>, _> >()?;
 
         assert_ne!(connections.len(), 0);
 
@@ -537,23 +2155,16 @@ mod tests
 
         assert!(connections[0].output().unwrap().heuristic(&target) == 0.0);
         
-        input = test_pairs.inputs;
-        
-        {
-            let mut func = input_neuron.function.write().unwrap();
-            *func = Arc::new(move |_inputs: &[NeuronValue]| {
-                Some(NeuronValue::Grids(input.clone()))
-            });
-        }
-        
-        assert!(connections[0].output().unwrap().heuristic(&NeuronValue::Grids(test_pairs.outputs)) == 0.0);
+        let context = Context::new().with("input", NeuronValue::Grids(test_pairs.inputs));
+
+        assert!(connections[0].output_in(&context).unwrap().heuristic(&NeuronValue::Grids(test_pairs.outputs)) == 0.0);
 
         Ok(())
     }
 
     #[tokio::test]
     async fn test_task253bf280() -> Result<(), Box<dyn std::error::Error> > {
-        let task = load_task("training", "253bf280").await?;
+        let task = data_source().load_task("training", "253bf280").await?;
         let train_pairs = input_output_pairs(&task.train);
         let test_pairs = input_option_output_pairs(&task.test);
 
@@ -577,7 +2188,7 @@ mod tests
 
                 match (&inputs[0], &inputs[1], &inputs[2], &inputs[3], &inputs[4]) {
                     (NeuronValue::Grids(a), NeuronValue::LocationPairs(b), NeuronValue::Int8(c), NeuronValue::Bool(d), NeuronValue::Bool(e)) => {
-                        Some(NeuronValue::Grids(segments(a, b, c.clone(), d.clone(), e.clone())))
+                        Some(NeuronValue::Grids(a.iter().zip(b).map(|(m, p)| segments(m, p, *c, *d, *e)).collect()))
                     }
                     _ => None,
                 }
@@ -657,9 +2268,7 @@ mod tests
             digit_neurons.push(neuron);
         }
 
-        let mut region_neurons : HashMap<i8, Arc<Neuron> > = HashMap::new();
-        let train_inputs = train_pairs.clone().inputs;
-        super::update_region_neurons(&mut region_neurons, &train_inputs.clone());
+        let region_neurons = super::solver::region_neurons(&train_pairs.inputs);
 
         let mut neurons: Vec<Arc<Neuron> > = vec![];
 
@@ -670,9 +2279,7 @@ mod tests
         neurons.push(true_neuron.clone());
         neurons.push(input_neuron.clone());
 
-        for (_k, v) in &region_neurons {
-            neurons.push(v.clone());
-        }
+        neurons.extend(region_neurons);
 
         {
             let mut digits: HashSet<i8> = HashSet::new();
@@ -704,7 +2311,7 @@ mod tests
         let target = NeuronValue::Grids(train_pairs.outputs);
 
         let brain: Brain = Brain::new(neurons);
-        let connections = brain.learn(&[target.clone()].to_vec(), 3, 1e-6);
+        let connections = brain.learn(&[target.clone()].to_vec(), 3, 1e-6).into_iter().collect::<Result<Vec<_>, _> >()?;
 
         assert_ne!(connections.len(), 0);
 
@@ -712,17 +2319,9 @@ mod tests
 
         assert!(connections[0].output().unwrap().heuristic(&target) == 0.0);
         
-        let test_inputs = test_pairs.clone().inputs;
-        super::update_region_neurons(&mut region_neurons, &test_inputs);
+        let context = super::solver::context(&test_pairs.inputs);
 
-        {
-            let mut func = input_neuron.function.write().unwrap();
-            *func = Arc::new(move |_inputs: &[NeuronValue]| {
-                Some(NeuronValue::Grids(test_inputs.clone()))
-            });
-        }
-        
-        assert!(connections[0].output().unwrap().heuristic(&NeuronValue::Grids(test_pairs.outputs)) == 0.0);
+        assert!(connections[0].output_in(&context).unwrap().heuristic(&NeuronValue::Grids(test_pairs.outputs)) == 0.0);
 
         Ok(())
     }
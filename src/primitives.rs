@@ -13,6 +13,35 @@ pub fn flipud(x: &[Array2<i8>]) -> Vec<Array2<i8> > {
     x.iter().map(airs::flipud).collect()
 }
 
+/// Rotate an array a quarter turn counterclockwise, `turns` times
+pub fn rotate(x: &Array2<i8>, turns: usize) -> Array2<i8> {
+    (0..turns % 4).fold(x.clone(), |z, _| airs::rot90(&z))
+}
+
+/// Let non-background cells of an array fall toward a side: 0 down, 1 right, 2 up, 3 left
+pub fn gravity(x: &Array2<i8>, side: usize) -> Array2<i8> {
+    // Counterclockwise quarter turns bringing the side down
+    let turns = [0, 3, 2, 1][side % 4];
+    let fallen = airs::gravity(&rotate(x, turns), 0);
+
+    rotate(&fallen, 4 - turns)
+}
+
+/// Complete the background cells of an array by mirroring across the vertical (`lr`) or horizontal axis
+pub fn complete_symmetry(x: &Array2<i8>, lr: bool) -> Array2<i8> {
+    if lr { airs::complete_symmetry_lr(x, 0) } else { airs::complete_symmetry_ud(x, 0) }
+}
+
+/// Replace a color by another in an array
+pub fn replace_color(x: &Array2<i8>, from: i8, to: i8) -> Array2<i8> {
+    airs::map(x, &HashMap::from([(from, to)]))
+}
+
+/// Fill the regions of an array with a value
+pub fn fill_regions(x: &Array2<i8>, regions: &[Vec<(isize, isize)>], value: i8) -> Array2<i8> {
+    regions.iter().fold(x.clone(), |z, region| airs::fill_region(&z, region, value))
+}
+
 /// Map cells of arrays from a mapping
 pub fn map(x: &Vec<Array2<i8> >, mapping: &HashMap<i8, i8>) -> Vec<Array2<i8> > {
     x.iter()
@@ -71,38 +100,28 @@ pub fn same_element(pairs: &Vec<Vec<((isize, isize), (isize, isize))> >, first:
     result
 }
 
-/// Segment two locations in arrays
-pub fn segments(dst: &Vec<Array2<i8> >, pairs: &Vec<Vec<((isize, isize), (isize, isize))> >, value: i8, start: bool, finish: bool) -> Vec<Array2<i8> > {
-    if dst.len() != pairs.len() {
-        return Vec::new();
-    }
-
-    let mut result = Vec::new();
-
-    for i in 0..dst.len() {
-        let mut m = dst[i].clone();
+/// Segment pairs of locations in an array
+pub fn segments(dst: &Array2<i8>, pairs: &[((isize, isize), (isize, isize))], value: i8, start: bool, finish: bool) -> Array2<i8> {
+    let mut m = dst.clone();
 
-        for &(p0, p1) in &pairs[i] {
-            if airs::valid_index(&m, p0) && airs::valid_index(&m, p1) {
-                let s = m[(p0.0 as usize, p0.1 as usize)];
-                let f = m[(p1.0 as usize, p1.1 as usize)];
+    for &(p0, p1) in pairs {
+        if airs::valid_index(&m, p0) && airs::valid_index(&m, p1) {
+            let s = m[(p0.0 as usize, p0.1 as usize)];
+            let f = m[(p1.0 as usize, p1.1 as usize)];
 
-                m = airs::dot_segment(&m, p0, p1, value, 1);
+            m = airs::dot_segment(&m, p0, p1, value, 1);
 
-                if start {
-                    m[(p0.0 as usize, p0.1 as usize)] = s;
-                }
+            if start {
+                m[(p0.0 as usize, p0.1 as usize)] = s;
+            }
 
-                if finish {
-                    m[(p1.0 as usize, p1.1 as usize)] = f;
-                }
+            if finish {
+                m[(p1.0 as usize, p1.1 as usize)] = f;
             }
         }
-
-        result.push(m);
     }
 
-    result
+    m
 }
 
 /// Pair a list of regions (a region is a list of connected pairs of same value, a pair is a grid location)
@@ -0,0 +1,224 @@
+use itertools::Itertools;
+use ndarray::Array2;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::airs;
+use crate::airs::{parse_connection, Brain, Connection, Context, Example, Neuron, NeuronRegistry, NeuronValue, ParseError, SearchConfig, SearchError, ValueType};
+use crate::arc::{input_option_output_pairs, input_output_pairs, Task};
+use crate::library;
+
+/// Program found for a task, or the search failure, and its predictions on the test inputs;
+/// `candidates` holds the test predictions of every ranked program, best first, and `failures` the train
+/// pairs the program fails when held out, if validated
+pub struct Solution {
+    pub program: Result<Arc<Connection>, SearchError>,
+    pub cost: f64,
+    pub predictions: Vec<Array2<i8> >,
+    pub candidates: Vec<Vec<Array2<i8> > >,
+    pub evaluations: usize,
+    pub exhausted: bool,
+    pub failures: Vec<usize>,
+}
+
+type RegionsList = Vec<Vec<Vec<(isize, isize)> > >;
+
+/// Regions of each color of each grid, by color
+fn color_regions(grids: &[Array2<i8>]) -> HashMap<i8, RegionsList> {
+    let mut region_map: HashMap<i8, RegionsList> = (0..10)
+        .map(|i| (i, Vec::with_capacity(grids.len())))
+        .collect();
+
+    for grid in grids {
+        let mut regions: HashMap<i8, Vec<Vec<(isize, isize)> > > = (0..10)
+            .map(|i| (i, Vec::new()))
+            .collect();
+
+        for r in airs::region_set(grid, false) {
+            let value = grid[(r[0].0 as usize, r[0].1 as usize)];
+            regions.entry(value).or_default().push(r);
+        }
+
+        for (k, v) in regions {
+            region_map.entry(k).or_default().push(v);
+        }
+    }
+
+    region_map
+}
+
+/// Constant neurons `region<k>` outputting the regions of color `k` of each grid, for the colors found in `grids`
+pub fn region_neurons(grids: &[Array2<i8>]) -> Vec<Arc<Neuron> > {
+    color_regions(grids)
+        .into_iter()
+        .filter(|(_, v)| v.iter().any(|l| !l.is_empty()))
+        .sorted_by_key(|(k, _)| *k)
+        .map(|(k, v)| {
+            Arc::new(Neuron::new(
+                format!("region{}", k),
+                RwLock::new(Arc::new(move |_inputs: &[NeuronValue]| Some(NeuronValue::RegionsList(v.clone())))),
+                vec![],
+                ValueType::RegionsList,
+            ))
+        })
+        .collect()
+}
+
+/// Context evaluating a program on `grids`: `input` outputs them and `region<k>` their regions of color `k`
+pub fn context(grids: &[Array2<i8>]) -> Context {
+    color_regions(grids).into_iter().fold(
+        Context::new().with("input", NeuronValue::Grids(grids.to_vec())),
+        |context, (k, v)| context.with(format!("region{}", k), NeuronValue::RegionsList(v)),
+    )
+}
+
+/// Context evaluating a program on the single `grid` of an example, `input` outputting it
+pub fn example_context(grid: &Array2<i8>) -> Context {
+    context(std::slice::from_ref(grid)).with("input", NeuronValue::Grid(grid.clone()))
+}
+
+/// Registry sketches are parsed against, the same for every task: the single-grid library, the generic
+/// neurons, and the `input`, `train_pairs` and `region<k>` neurons whose values each example binds
+pub fn sketch_registry() -> NeuronRegistry {
+    let mut registry = library::example_registry();
+
+    registry
+        .register_all(library::generic_neurons())
+        .expect("Generic neuron names are unique in the library");
+
+    let variables = [("input".to_string(), ValueType::Grid), ("train_pairs".to_string(), ValueType::PairGrids)]
+        .into_iter()
+        .chain((0..10).map(|k| (format!("region{}", k), ValueType::RegionsList)));
+
+    registry
+        .register_all(variables.map(|(name, value_type)| Arc::new(Neuron::variable(name, value_type))))
+        .expect("No library neuron is named after a task neuron");
+
+    registry
+}
+
+/// Parse a sketch searched on every task, holes written as types
+pub fn parse_sketch(text: &str) -> Result<Arc<Connection>, ParseError> {
+    parse_connection(text, &sketch_registry())
+}
+
+/// Leave-one-out folds of the train pairs: fold `i` evaluates a program on the input of pair `i` with
+/// `train_pairs` bound to the other pairs; no fold below two pairs
+pub fn leave_one_out(task: &Task) -> Vec<Example> {
+    if task.train.len() < 2 {
+        return Vec::new();
+    }
+
+    task.train
+        .iter()
+        .enumerate()
+        .map(|(i, (input, output))| {
+            let others = task.train
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, pair)| pair.clone())
+                .collect();
+
+            Example::new(
+                example_context(input).with("train_pairs", NeuronValue::PairGrids(others)),
+                NeuronValue::Grid(output.clone()),
+            )
+        })
+        .collect()
+}
+
+/// Single-grid neurons used to solve ARC tasks on each example, `input` and `region` neurons excepted
+pub fn grid_neurons(task: &Task) -> Vec<Arc<Neuron> > {
+    let library = library::example_registry();
+
+    let mut neurons: Vec<Arc<Neuron> > = [
+        "fliplr",
+        "flipud",
+        "infer_color_mapping",
+        "map",
+        "segments",
+        "same_element",
+        "region_pairs",
+        "false",
+        "true",
+    ]
+    .iter()
+    .map(|name| library.get(name).expect("Library neuron").clone())
+    .collect();
+
+    // Bound by the context of each example like `input`; the order of the neurons only decides which of equally
+    // cheap programs an exhaustive search stopping early meets first
+    neurons.push(Arc::new(Neuron::variable("train_pairs", ValueType::PairGrids)));
+
+    let mut digits: HashSet<i8> = HashSet::new();
+
+    for (input, output) in &task.train {
+        digits.extend(input.iter());
+        digits.extend(output.iter());
+    }
+
+    for i in 0..10 {
+        if digits.contains(&i) {
+            neurons.push(library.get(&i.to_string()).expect("Library digit neuron").clone());
+        }
+    }
+
+    neurons
+}
+
+/// Search a program mapping train inputs to train outputs and apply it to test inputs; the program is
+/// searched on each train pair, its neurons working on the grids of a single pair, and only the shapes of
+/// the sketches of `config` are searched if any
+pub fn solve(task: &Task, config: &SearchConfig) -> Solution {
+    let train_pairs = input_output_pairs(&task.train);
+    let test_pairs = input_option_output_pairs(&task.test);
+
+    // Bound by the context of each example, the values of `input` and the region neurons only type them
+    let input_neuron = Arc::new(Neuron::variable("input", ValueType::Grid));
+
+    let mut neurons = grid_neurons(task);
+    neurons.push(input_neuron);
+    neurons.extend(region_neurons(&train_pairs.inputs));
+
+    let context = |grid| example_context(grid).with("train_pairs", NeuronValue::PairGrids(task.train.clone()));
+    let examples: Vec<Example> = task
+        .train
+        .iter()
+        .map(|(input, output)| Example::new(context(input), NeuronValue::Grid(output.clone())))
+        .collect();
+
+    let search = Brain::new(neurons).search_examples(&examples, config);
+    let program = search.connections[0].clone();
+    let cost = match (&program, search.ranked[0].first()) {
+        (Ok(_), Some(pair)) => pair.cost,
+        _ => f64::INFINITY,
+    };
+
+    let candidates: Vec<Vec<Array2<i8> > > = search.ranked[0]
+        .iter()
+        .map(|pair| {
+            test_pairs
+                .inputs
+                .iter()
+                .map(|input| match pair.connection.output_in(&context(input)) {
+                    Some(NeuronValue::Grid(grid)) => Some(grid),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_> > >()
+                .unwrap_or_default()
+        })
+        .collect();
+    let predictions = candidates.first().cloned().unwrap_or_default();
+    let failures = search.ranked[0].first().map(|pair| pair.failures.clone()).unwrap_or_default();
+
+    Solution {
+        program,
+        cost,
+        predictions,
+        candidates,
+        evaluations: search.evaluations,
+        exhausted: search.exhausted,
+        failures,
+    }
+}
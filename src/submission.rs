@@ -0,0 +1,69 @@
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::arc::Task;
+
+/// Two attempts for one test input, as expected by the ARC Prize submission format
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attempts {
+    pub attempt_1: Vec<Vec<i8> >,
+    pub attempt_2: Vec<Vec<i8> >,
+}
+
+/// Predictions of several tasks, written as `submission.json` (task id → attempts per test input)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Submission {
+    pub tasks: BTreeMap<String, Vec<Attempts> >,
+}
+
+fn to_rows(grid: &Array2<i8>) -> Vec<Vec<i8> > {
+    grid.rows().into_iter().map(|row| row.to_vec()).collect()
+}
+
+impl Submission {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the attempts of a task from the test predictions of its candidate programs, best first;
+    /// the two first distinct grids are kept for each test input, defaulting to the input grid
+    pub fn add(&mut self, task_id: &str, task: &Task, candidates: &[Vec<Array2<i8> >]) {
+        let mut attempts = Vec::with_capacity(task.test.len());
+
+        for (i, (input, _)) in task.test.iter().enumerate() {
+            let mut grids: Vec<&Array2<i8> > = Vec::new();
+
+            for predictions in candidates {
+                if let Some(grid) = predictions.get(i) {
+                    if !grid.is_empty() && !grids.contains(&grid) {
+                        grids.push(grid);
+                    }
+                }
+
+                if grids.len() == 2 {
+                    break;
+                }
+            }
+
+            let attempt_1 = grids.first().copied().unwrap_or(input);
+            let attempt_2 = grids.get(1).copied().unwrap_or(attempt_1);
+
+            attempts.push(Attempts {
+                attempt_1: to_rows(attempt_1),
+                attempt_2: to_rows(attempt_2),
+            });
+        }
+
+        self.tasks.insert(task_id.to_string(), attempts);
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error> > {
+        fs::write(path, serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+}